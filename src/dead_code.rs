@@ -0,0 +1,308 @@
+use std::collections::HashSet;
+
+use crate::types::{AppData, MethodOwner, MethodRef, Visibility};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeadCode {
+    HelperModule(String),
+    ConcernMethod { concern: String, method: String },
+    ControllerMethod { controller: String, method: String },
+    UnusedConcern(String),
+}
+
+impl std::fmt::Display for DeadCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadCode::HelperModule(name) => write!(f, "helper {} is never used", name),
+            DeadCode::ConcernMethod { concern, method } => {
+                write!(f, "concern method {}#{} is never called", concern, method)
+            }
+            DeadCode::ControllerMethod { controller, method } => {
+                write!(
+                    f,
+                    "controller method {}#{} is never called",
+                    controller, method
+                )
+            }
+            DeadCode::UnusedConcern(name) => write!(f, "concern {} is never included", name),
+        }
+    }
+}
+
+// every method reachable from a routed action, one of its controller's callbacks and
+// rescue_from handlers or its views
+fn find_reachable(app_data: &AppData) -> HashSet<MethodRef> {
+    let mut reachable = HashSet::new();
+    for route in app_data.routes.iter() {
+        let controller = match route.get_controller(app_data) {
+            Ok(controller) => controller,
+            Err(_) => continue,
+        };
+        let table = match controller.get_method_table(app_data) {
+            Some(table) => table,
+            None => continue,
+        };
+        let roots = std::iter::once(&route.action)
+            .chain(controller.get_callbacks(app_data).into_iter().map(|x| &x.1))
+            .chain(
+                controller
+                    .get_rescue_handlers(app_data)
+                    .into_iter()
+                    .map(|x| &x.method),
+            );
+        for root in roots {
+            if let Some(method) = table.get(root) {
+                reachable.extend(table.get_reachable(method, app_data));
+            }
+        }
+
+        // helpers the route's views call, along with the helpers those call
+        let mut helpers: Vec<MethodRef> = route
+            .get_responses(app_data)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|x| route.get_template(app_data, x))
+            .flat_map(|view| &view.helper_calls)
+            .filter_map(|name| route.find_view_helper(app_data, name))
+            .collect();
+        while let Some(helper) = helpers.pop() {
+            if !reachable.insert(helper.clone()) {
+                continue;
+            }
+            if let Some(method) = app_data.get_method(&helper) {
+                if table.get(&method.name) == Some(&helper) {
+                    reachable.extend(table.get_reachable(&helper, app_data));
+                }
+                helpers.extend(
+                    method
+                        .method_calls
+                        .iter()
+                        .filter(|x| x.is_local())
+                        .filter_map(|x| route.find_view_helper(app_data, &x.name)),
+                );
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Helpers, concern methods and private controller methods that no route can reach
+/// along with concerns that are never included.
+pub fn find_dead_code(app_data: &AppData) -> Vec<DeadCode> {
+    let reachable = find_reachable(app_data);
+    let is_reachable =
+        |owner: MethodOwner, index: usize| reachable.contains(&MethodRef { owner, index });
+    let mut results = Vec::new();
+
+    for helper in app_data.helpers.values() {
+        if !(0..helper.methods.len())
+            .any(|index| is_reachable(MethodOwner::Helper(helper.name.clone()), index))
+        {
+            results.push(DeadCode::HelperModule(helper.name.clone()));
+        }
+    }
+
+    let included: HashSet<&MethodOwner> = app_data
+        .method_tables
+        .values()
+        .flat_map(|table| &table.ancestors)
+        .collect();
+    for concern in app_data.concerns.values() {
+        if !included.contains(&MethodOwner::Concern(concern.name.clone())) {
+            results.push(DeadCode::UnusedConcern(concern.name.clone()));
+            continue;
+        }
+        for (index, method) in concern.methods.iter().enumerate() {
+            if !is_reachable(MethodOwner::Concern(concern.name.clone()), index) {
+                results.push(DeadCode::ConcernMethod {
+                    concern: concern.name.clone(),
+                    method: method.name.clone(),
+                });
+            }
+        }
+    }
+
+    for controller in app_data.controllers.values() {
+        for (index, method) in controller.methods.iter().enumerate() {
+            if method.visibility != Visibility::Public
+                && !is_reachable(MethodOwner::Controller(controller.name.clone()), index)
+            {
+                results.push(DeadCode::ControllerMethod {
+                    controller: controller.name.clone(),
+                    method: method.name.clone(),
+                });
+            }
+        }
+    }
+
+    results.sort();
+    results
+}
+
+#[cfg(test)]
+mod dead_code_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::find_dead_code;
+    use crate::{
+        app_data_from_source,
+        routes::{parse_segments, Request, RequestMethod},
+        views::{resolve_partials, view_from_source},
+    };
+
+    // `views` are `(dir, name, source)` jbuilder templates
+    fn helper(sources: &[&str], views: &[(&str, &str, &str)]) -> Vec<String> {
+        let mut app_data = app_data_from_source(sources);
+        for (dir, name, source) in views {
+            app_data
+                .views
+                .entry(dir.to_string())
+                .or_default()
+                .insert(name.to_string(), view_from_source(dir, name, source));
+        }
+        resolve_partials(&mut app_data.views);
+        app_data.routes.push(Request {
+            methods: vec![RequestMethod::Get],
+            prefix: "".to_owned(),
+            uri: "/pages".to_owned(),
+            segments: parse_segments("/pages"),
+            controller: "pages_controller".to_owned(),
+            action: "index".to_owned(),
+        });
+        find_dead_code(&app_data)
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn dead_code() {
+        let application = "
+        class ApplicationController < ActionController::API
+            include HttpResponses
+
+            before_action :auth_check
+
+            def auth_check
+                check_token
+            end
+
+            private
+
+            def check_token
+                params[:auth_token]
+            end
+
+            def old_check
+            end
+        end
+        ";
+        let concern = "
+        module HttpResponses
+            extend ActiveSupport::Concern
+
+            def json_ok(obj)
+                render :json => obj
+            end
+
+            def json_error(obj)
+                render :json => obj, :status => 500
+            end
+        end
+        ";
+        let unused_concern = "
+        module ErrorHandling
+            extend ActiveSupport::Concern
+
+            def catch_exceptions
+                yield
+            end
+        end
+        ";
+        let used_helper = "
+        module PageHelper
+            def blog_category
+                Blogs.find(params[:cat])
+            end
+        end
+        ";
+        let unused_helper = "
+        module LegacyHelper
+            def legacy
+            end
+        end
+        ";
+        let pages = "
+        class PagesController < ApplicationController
+            include PageHelper
+            include LegacyHelper
+
+            def index
+                json_ok(blog_category)
+            end
+
+            private
+
+            def unused
+                json_error(1)
+            end
+        end
+        ";
+        assert_eq!(
+            helper(
+                &[
+                    application,
+                    concern,
+                    unused_concern,
+                    used_helper,
+                    unused_helper,
+                    pages
+                ],
+                &[]
+            ),
+            [
+                "helper LegacyHelper is never used",
+                "concern method HttpResponses#json_error is never called",
+                "controller method ApplicationController#old_check is never called",
+                "controller method PagesController#unused is never called",
+                "concern ErrorHandling is never included",
+            ]
+        );
+    }
+
+    #[test]
+    fn view_helpers() {
+        let pages = "
+        class PagesController < ApplicationController
+            def index
+                @post = Post.first
+            end
+        end
+        ";
+        let pages_helper = "
+        module PagesHelper
+            def status_label(post)
+                label_for(post.status)
+            end
+
+            def label_for(status)
+                status.to_s.humanize
+            end
+
+            def unused_label
+            end
+        end
+        ";
+        assert_eq!(
+            helper(
+                &[pages, pages_helper],
+                &[
+                    ("pages", "index", "json.partial! 'post', post: @post"),
+                    ("pages", "_post", "json.status status_label(post)")
+                ]
+            ),
+            Vec::<String>::new()
+        );
+    }
+}
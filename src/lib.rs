@@ -1,10 +1,19 @@
+mod dead_code;
+mod lint;
+mod models;
 mod params;
+mod responses;
 mod routes;
+mod routes_dsl;
+mod serializers;
 mod types;
 mod utils;
 mod views;
 
-use types::{ActionKinds, AppData, Concern, Controller, HelperModule, MethodDetails};
+use types::{
+    ActionKinds, AppData, Concern, Controller, HelperModule, MethodDetails, RescueHandler,
+    Visibility,
+};
 
 use std::{
     collections::{HashMap, VecDeque},
@@ -12,15 +21,21 @@ use std::{
     path::Path,
 };
 
+use convert_case::{Case, Casing};
 use lib_ruby_parser::{
-    nodes::{Class, Send},
+    nodes::{Block, Class, Defs, Hash, Kwargs, Nil, Send},
+    source::Range,
     Node, Parser,
 };
 
 use utils::{get_node_name, parse_name, parse_superclass};
-use walkdir::{DirEntry, WalkDir};
+use walkdir::WalkDir;
+
+use crate::routes::parse_routes;
 
-use crate::routes::{parse_routes, Request};
+pub use dead_code::{find_dead_code, DeadCode};
+pub use lint::{lint, Finding};
+pub use types::FieldType;
 
 #[derive(Debug)]
 enum File {
@@ -30,24 +45,74 @@ enum File {
     None,
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
-}
-
 fn get_method_details_from_optional(
     optional_args: Option<Box<Node>>,
+    def_args: Option<Box<Node>>,
     name: String,
+    visibility: Visibility,
     methods: &mut Vec<MethodDetails>,
-) -> Option<MethodDetails> {
-    if let Some(arg) = optional_args {
-        methods.push(params::create_method_details(arg, name, Vec::new()));
+) {
+    let args = params::parse_args(&def_args);
+    let arg_names: Vec<String> = args.iter().map(|(arg, _)| arg.clone()).collect();
+    let mut method = match optional_args {
+        Some(arg) => params::create_method_details(arg, name, arg_names),
+        // empty methods can still be actions
+        None => params::create_method_details(
+            Box::new(Node::Nil(Nil {
+                expression_l: Range::new(0, 0),
+            })),
+            name,
+            arg_names,
+        ),
+    };
+    for (arg, offset) in args {
+        method
+            .local_varaible_offsets
+            .insert(arg.trim_start_matches(['*', '&']).to_owned(), offset);
     }
+    method.visibility = visibility;
+    methods.push(method);
+}
 
-    None
+// `def self.foo`, always public but never an action
+fn get_class_method_details(stat: Defs, methods: &mut Vec<MethodDetails>) {
+    get_method_details_from_optional(stat.body, stat.args, stat.name, Visibility::Public, methods);
+    if let Some(method) = methods.last_mut() {
+        method.class_method = true;
+    }
+}
+
+// handles `private`, `private :foo, :bar` and `private def foo`
+fn parse_visibility(
+    send_thing: Send,
+    visibility: &mut Visibility,
+    methods: &mut Vec<MethodDetails>,
+) {
+    let new_visibility = match send_thing.method_name.as_str() {
+        "private" => Visibility::Private,
+        "protected" => Visibility::Protected,
+        _ => Visibility::Public,
+    };
+    if send_thing.args.is_empty() {
+        *visibility = new_visibility;
+    }
+    for arg in send_thing.args {
+        match arg {
+            Node::Def(stat) => get_method_details_from_optional(
+                stat.body,
+                stat.args,
+                stat.name,
+                new_visibility,
+                methods,
+            ),
+            _ => {
+                let name = utils::parse_node_str(&arg);
+                for method in methods.iter_mut().filter(|method| method.name == name) {
+                    method.visibility = new_visibility;
+                }
+            }
+        }
+    }
 }
 
 fn parse_actions(send_thing: Send, actions: &mut Vec<(ActionKinds, String)>) {
@@ -62,11 +127,6 @@ fn parse_actions(send_thing: Send, actions: &mut Vec<(ActionKinds, String)>) {
                 actions.push((ActionKinds::AroundAction, utils::parse_node_str(arg)));
             }
         }
-        "rescue_from" => {
-            for arg in &send_thing.args {
-                actions.push((ActionKinds::RescueFrom, utils::parse_node_str(arg)));
-            }
-        }
         _ => {
             // for arg in &send_thing.args {
             //     actions.push((
@@ -78,8 +138,81 @@ fn parse_actions(send_thing: Send, actions: &mut Vec<(ActionKinds, String)>) {
     }
 }
 
+// `helper :all`, `helper :posts`, `helper 'admin/posts'` and `helper PostsHelper`
+fn parse_helper(node: &Node) -> String {
+    match node {
+        Node::Const(_) => utils::parse_node_str(node),
+        node => match utils::parse_node_str(node).as_str() {
+            "all" => "all".to_owned(),
+            name => format!(
+                "{}Helper",
+                name.split('/')
+                    .map(|x| x.to_case(Case::Pascal))
+                    .collect::<Vec<_>>()
+                    .join("::")
+            ),
+        },
+    }
+}
+
+// `rescue_from A, B, with: :handler`, `rescue_from A, with: ->(e) { ... }` and
+// `rescue_from A do |e| ... end`
+fn parse_rescue_from(
+    send_thing: Send,
+    block: Option<Block>,
+    rescues: &mut Vec<RescueHandler>,
+    methods: &mut Vec<MethodDetails>,
+) {
+    let mut exceptions = Vec::new();
+    let mut handler = None;
+    for arg in send_thing.args {
+        match arg {
+            Node::Kwargs(Kwargs { pairs, .. }) | Node::Hash(Hash { pairs, .. }) => {
+                for pair in pairs {
+                    if let Node::Pair(pair) = pair {
+                        if utils::parse_node_str(&pair.key) == "with" {
+                            handler = Some(*pair.value);
+                        }
+                    }
+                }
+            }
+            arg => exceptions.push(utils::parse_node_str(&arg)),
+        }
+    }
+
+    let name = format!("rescue_from {}", exceptions.join(", "));
+    let method = match (handler, block) {
+        (Some(handler @ (Node::Sym(_) | Node::Str(_))), _) => utils::parse_node_str(&handler),
+        (Some(Node::Block(block)), _) | (None, Some(block)) => {
+            get_method_details_from_optional(
+                block.body,
+                block.args,
+                name.clone(),
+                Visibility::Private,
+                methods,
+            );
+            name
+        }
+        _ => {
+            println!("WARNING: could not find the handler for {}", name);
+            return;
+        }
+    };
+    rescues.push(RescueHandler { exceptions, method });
+}
+
+// `Api` and `V1` is `Api::V1`
+fn join_module(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}::{}", module, name)
+    }
+}
+
+// controllers are named by their full path so ones in different namespaces don't collide
 fn parse_class(class: Class, module: String) -> Result<File, String> {
-    let name = parse_name(*class.name);
+    let name = join_module(&module, &parse_name(*class.name));
     let superclass = parse_superclass(class.superclass);
     if superclass.is_empty() {
         Err("single file classes not supported".to_string())
@@ -87,17 +220,28 @@ fn parse_class(class: Class, module: String) -> Result<File, String> {
         if let Some(body) = class.body {
             let mut methods = Vec::new();
             let mut includes = Vec::new();
+            let mut prepends = Vec::new();
             let mut actions = Vec::new();
+            let mut rescues = Vec::new();
+            let mut helpers = Vec::new();
+            let mut helper_methods = Vec::new();
             match *body {
                 // def and defs .name and we need to consider the argument names it takes.... but I haven't thought about args
                 Node::Def(stat) => {
-                    get_method_details_from_optional(stat.body, stat.name, &mut methods);
+                    get_method_details_from_optional(
+                        stat.body,
+                        stat.args,
+                        stat.name,
+                        Visibility::Public,
+                        &mut methods,
+                    );
                 }
                 Node::Defs(stat) => {
-                    get_method_details_from_optional(stat.body, stat.name, &mut methods);
+                    get_class_method_details(stat, &mut methods);
                 }
 
                 Node::Begin(stat) => {
+                    let mut visibility = Visibility::Public;
                     for stat in stat.statements {
                         match stat {
                             Node::Send(send_thing) => match send_thing.method_name.as_str() {
@@ -110,23 +254,48 @@ fn parse_class(class: Class, module: String) -> Result<File, String> {
                                         }
                                     }
                                 }
-                                "private" => {}
-                                "protected" => {}
+                                "prepend" => {
+                                    for arg in &send_thing.args {
+                                        prepends.push(utils::parse_node_str(arg));
+                                    }
+                                }
+                                "private" | "protected" | "public" => {
+                                    parse_visibility(send_thing, &mut visibility, &mut methods)
+                                }
+                                "rescue_from" => {
+                                    parse_rescue_from(send_thing, None, &mut rescues, &mut methods)
+                                }
+                                "helper" => {
+                                    helpers.extend(send_thing.args.iter().map(parse_helper))
+                                }
+                                "helper_method" => helper_methods
+                                    .extend(send_thing.args.iter().map(utils::parse_node_str)),
                                 _ => parse_actions(send_thing, &mut actions),
                             },
+                            // blocks like `with_options` don't change what a route responds with
+                            Node::Block(block) => {
+                                if let Node::Send(send_thing) = *block.call.clone() {
+                                    if send_thing.method_name == "rescue_from" {
+                                        parse_rescue_from(
+                                            send_thing,
+                                            Some(block),
+                                            &mut rescues,
+                                            &mut methods,
+                                        )
+                                    }
+                                }
+                            }
                             Node::Def(stat) => {
                                 get_method_details_from_optional(
                                     stat.body,
+                                    stat.args,
                                     stat.name,
+                                    visibility,
                                     &mut methods,
                                 );
                             }
                             Node::Defs(stat) => {
-                                get_method_details_from_optional(
-                                    stat.body,
-                                    stat.name,
-                                    &mut methods,
-                                );
+                                get_class_method_details(stat, &mut methods);
                             }
                             Node::Casgn(_) => {
                                 // END_USER_ALLOWED_SETTINGS
@@ -146,7 +315,11 @@ fn parse_class(class: Class, module: String) -> Result<File, String> {
                 parent: superclass,
                 methods,
                 actions,
+                rescues,
                 include: includes,
+                prepend: prepends,
+                helpers,
+                helper_methods,
                 module: if module.is_empty() {
                     None
                 } else {
@@ -159,7 +332,11 @@ fn parse_class(class: Class, module: String) -> Result<File, String> {
                 parent: superclass,
                 methods: Vec::new(),
                 actions: Vec::new(),
+                rescues: Vec::new(),
                 include: Vec::new(),
+                prepend: Vec::new(),
+                helpers: Vec::new(),
+                helper_methods: Vec::new(),
                 module: if module.is_empty() {
                     None
                 } else {
@@ -187,11 +364,17 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
                 if let Some(body) = module.body {
                     buf.push_back(*body);
                 }
-                module_names.push_back(module_name.clone() + &parse_name(*module.name));
+                module_names.push_back(join_module(&module_name, &parse_name(*module.name)));
             }
             Node::Def(stat) => {
                 let mut methods = Vec::new();
-                get_method_details_from_optional(stat.body, stat.name, &mut methods);
+                get_method_details_from_optional(
+                    stat.body,
+                    stat.args,
+                    stat.name,
+                    Visibility::Public,
+                    &mut methods,
+                );
                 files.push(File::Module(HelperModule {
                     name: module_name.clone(),
                     methods,
@@ -199,7 +382,7 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
             }
             Node::Defs(stat) => {
                 let mut methods = Vec::new();
-                get_method_details_from_optional(stat.body, stat.name, &mut methods);
+                get_class_method_details(stat, &mut methods);
                 files.push(File::Module(HelperModule {
                     name: module_name.clone(),
                     methods,
@@ -211,13 +394,16 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
                 let mut concern_found = false;
                 let mut methods = Vec::<MethodDetails>::new();
                 let mut actions = Vec::new();
+                let mut rescues = Vec::new();
+                let mut visibility = Visibility::Public;
                 for stat in begin.statements {
                     match stat {
                         Node::Module(module) => {
                             if let Some(body) = module.body {
                                 buf.push_back(*body);
                             }
-                            module_names.push_back(module_name.clone() + &parse_name(*module.name));
+                            module_names
+                                .push_back(join_module(&module_name, &parse_name(*module.name)));
                         }
                         Node::Class(class) => {
                             files.push(parse_class(class, module_name.clone())?);
@@ -229,7 +415,6 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
                                         && get_node_name(&send.args[0])? == "ActiveSupport::Concern"
                                     {
                                         concern_found = true;
-                                        break;
                                     } else {
                                         return Err("unsupported 'extend' found".to_owned());
                                     }
@@ -237,7 +422,9 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
 
                                 //TODO: require
                                 "require" => {}
-                                "private" => {}
+                                "private" | "protected" | "public" => {
+                                    parse_visibility(send, &mut visibility, &mut methods)
+                                }
                                 "private_class_method" => {
                                     // TODOD: check if the method is an arg for this!!!
                                 }
@@ -249,13 +436,19 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
                         }
                         Node::Casgn(_) => {}
                         Node::Def(stat) => {
-                            get_method_details_from_optional(stat.body, stat.name, &mut methods);
+                            get_method_details_from_optional(
+                                stat.body,
+                                stat.args,
+                                stat.name,
+                                visibility,
+                                &mut methods,
+                            );
                             if !concern_found {
                                 helper_found = true;
                             }
                         }
                         Node::Defs(stat) => {
-                            get_method_details_from_optional(stat.body, stat.name, &mut methods);
+                            get_class_method_details(stat, &mut methods);
                             if !concern_found {
                                 helper_found = true;
                             }
@@ -267,9 +460,41 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
                                 }
                                 if let Node::Send(stat) = *block.call {
                                     if stat.method_name == "included" {
-                                        if let Some(arg) = block.args {
-                                            if let Node::Send(action_stat) = *arg {
-                                                parse_actions(action_stat, &mut actions)
+                                        let statements = match block.body.map(|body| *body) {
+                                            Some(Node::Begin(body)) => body.statements,
+                                            Some(stat) => vec![stat],
+                                            None => Vec::new(),
+                                        };
+                                        for stat in statements {
+                                            match stat {
+                                                Node::Send(action_stat)
+                                                    if action_stat.method_name == "rescue_from" =>
+                                                {
+                                                    parse_rescue_from(
+                                                        action_stat,
+                                                        None,
+                                                        &mut rescues,
+                                                        &mut methods,
+                                                    )
+                                                }
+                                                Node::Send(action_stat) => {
+                                                    parse_actions(action_stat, &mut actions)
+                                                }
+                                                Node::Block(block) => match *block.call.clone() {
+                                                    Node::Send(action_stat)
+                                                        if action_stat.method_name
+                                                            == "rescue_from" =>
+                                                    {
+                                                        parse_rescue_from(
+                                                            action_stat,
+                                                            Some(block),
+                                                            &mut rescues,
+                                                            &mut methods,
+                                                        )
+                                                    }
+                                                    _ => {}
+                                                },
+                                                _ => {}
                                             }
                                         }
                                     } else {
@@ -305,6 +530,7 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
                         name: module_name.clone(),
                         methods,
                         actions,
+                        rescues,
                     }));
                 }
             }
@@ -318,6 +544,18 @@ fn parse_file(node: Node) -> Result<Vec<File>, String> {
     Ok(files)
 }
 
+fn set_file(file: &mut File, name: &str) {
+    let methods = match file {
+        File::Controller(controller) => &mut controller.methods,
+        File::Module(module) => &mut module.methods,
+        File::Concern(concern) => &mut concern.methods,
+        File::None => return,
+    };
+    for method in methods {
+        method.file = name.to_owned();
+    }
+}
+
 fn parse_files(
     path: &Path,
     controllers: &mut HashMap<String, Controller>,
@@ -328,7 +566,7 @@ fn parse_files(
     let mut file_count = 0;
     for entry in WalkDir::new(path)
         .into_iter()
-        .filter_entry(|e| -> bool { !is_hidden(e) })
+        .filter_entry(|e| -> bool { !utils::is_hidden(e) })
         .filter_map(|e| e.ok())
     {
         let f = entry.path();
@@ -340,7 +578,8 @@ fn parse_files(
                     let result = parse_file(node);
                     match result {
                         Ok(result) => {
-                            for cat in result {
+                            for mut cat in result {
+                                set_file(&mut cat, &name);
                                 match cat {
                                     File::Controller(controller) => {
                                         controllers.insert(controller.name.clone(), controller);
@@ -393,23 +632,23 @@ fn parse_files(
 pub fn compute(root: &Path) -> Result<AppData, Box<dyn std::error::Error>> {
     let mut route_path = root.to_path_buf();
     route_path.push("test.routes");
-    if !route_path.exists() {
-        return Err("no test.routes file found in root of rails project directory, run `bundle exec rails r routes > test.routes` to generate the file".into());
-    }
-
-    let mut routes: HashMap<String, Request> = HashMap::new();
-    for route in parse_routes(&fs::read_to_string(route_path).unwrap())? {
-        routes.insert(route.uri.clone(), route);
-    }
-
-    let mut app_data = AppData {
-        concerns: HashMap::new(),
-        helpers: HashMap::new(),
-        controllers: HashMap::new(),
-        routes,
-        views: HashMap::new(),
+    let mut routes_file_path = root.to_path_buf();
+    routes_file_path.push("config");
+    routes_file_path.push("routes.rb");
+    let (requests, mounts) = if route_path.exists() {
+        (
+            parse_routes(&fs::read_to_string(route_path).unwrap())?,
+            Vec::new(),
+        )
+    } else if routes_file_path.exists() {
+        routes_dsl::parse_routes_file(root)?
+    } else {
+        return Err("no test.routes or config/routes.rb file found in root of rails project directory, run `bundle exec rails r routes > test.routes` to generate the file".into());
     };
 
+    let mut app_data = AppData::new(requests);
+    app_data.mounts = mounts;
+
     let mut app_dir = root.to_path_buf();
     app_dir.push("app");
 
@@ -419,6 +658,12 @@ pub fn compute(root: &Path) -> Result<AppData, Box<dyn std::error::Error>> {
     let mut controllers_path = app_dir.clone();
     controllers_path.push("controllers");
 
+    let mut serializers_path = app_dir.clone();
+    serializers_path.push("serializers");
+
+    let mut models_path = app_dir.clone();
+    models_path.push("models");
+
     let mut view_path = app_dir;
     view_path.push("views");
 
@@ -435,16 +680,61 @@ pub fn compute(root: &Path) -> Result<AppData, Box<dyn std::error::Error>> {
         &mut app_data.helpers,
     )?;
 
-    views::parse_view_files(&view_path, &mut app_data.views)?;
+    let mut initializers_path = root.to_path_buf();
+    initializers_path.push("config");
+    initializers_path.push("initializers");
+    let key_format = views::parse_key_format(&initializers_path)?;
+
+    views::parse_view_files(&view_path, &key_format, &mut app_data.views)?;
+    serializers::parse_serializer_files(&serializers_path, &mut app_data.serializers)?;
+    models::parse_model_files(&models_path, &mut app_data.models)?;
+
+    let mut schema_path = root.to_path_buf();
+    schema_path.push("db");
+    schema_path.push("schema.rb");
+    models::parse_schema_file(&schema_path, &mut app_data.tables)?;
+
+    app_data.build_method_tables();
 
     Ok(app_data)
 }
 
+// builds up app data from ruby source for tests that need more than a single file
+#[cfg(test)]
+pub(crate) fn app_data_from_source(sources: &[&str]) -> AppData {
+    let mut app_data = AppData::new(Vec::new());
+    for source in sources {
+        let node = Parser::new(source.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap();
+        for file in parse_file(node).unwrap() {
+            match file {
+                File::Controller(controller) => {
+                    app_data
+                        .controllers
+                        .insert(controller.name.clone(), controller);
+                }
+                File::Module(module) => {
+                    app_data.helpers.insert(module.name.clone(), module);
+                }
+                File::Concern(concern) => {
+                    app_data.concerns.insert(concern.name.clone(), concern);
+                }
+                File::None => {}
+            }
+        }
+    }
+    app_data.build_method_tables();
+    app_data
+}
+
 #[cfg(test)]
 mod parse_class_tests {
-    use lib_ruby_parser::{Node, Parser};
+    use lib_ruby_parser::{nodes::Class, Node, Parser};
+    use pretty_assertions::assert_eq;
 
-    use crate::parse_class;
+    use crate::{parse_class, types::Visibility, File};
 
     fn helper(input: &str) -> Box<lib_ruby_parser::Node> {
         Box::new(
@@ -454,6 +744,15 @@ mod parse_class_tests {
                 .unwrap(),
         )
     }
+
+    fn class_helper(input: &str) -> Class {
+        if let Node::Class(class) = *helper(input) {
+            class
+        } else {
+            panic!("expected a class");
+        }
+    }
+
     #[test]
     fn basic() {
         let input = "
@@ -467,11 +766,234 @@ mod parse_class_tests {
             end
         end
         ";
-        if let Node::Class(value) = *helper(input) {
-            println!("{:?}", parse_class(value, "".to_string()));
+        if let Ok(File::Controller(controller)) = parse_class(class_helper(input), "".to_string()) {
+            assert_eq!(controller.name, "ApplicationController");
+            assert_eq!(controller.parent, "ActionController::API");
+            assert_eq!(controller.include, ["HttpResponses"]);
+            assert_eq!(controller.actions.len(), 1);
+            assert_eq!(controller.actions[0].1, "auth_check");
+            assert_eq!(controller.methods[0].name, "auth_check");
+            assert_eq!(controller.methods[0].params.len(), 1);
+        } else {
+            panic!("expected a controller");
+        }
+    }
+
+    #[test]
+    fn rescue_from() {
+        let input = "
+        class ApplicationController < ActionController::API
+            rescue_from ActiveRecord::RecordNotFound, ActiveRecord::RecordInvalid, with: :not_found
+            rescue_from Pundit::NotAuthorizedError, with: ->(e) { head :forbidden }
+            rescue_from ActionController::ParameterMissing do |e|
+                render json: { error: e.message }, status: :bad_request
+            end
+
+            private
+
+            def not_found
+                head :not_found
+            end
+        end
+        ";
+        if let Ok(File::Controller(controller)) = parse_class(class_helper(input), "".to_string()) {
+            assert!(controller.actions.is_empty());
+            assert_eq!(
+                controller
+                    .rescues
+                    .iter()
+                    .map(|x| (x.exceptions.join(","), x.method.as_str()))
+                    .collect::<Vec<_>>(),
+                [
+                    (
+                        "ActiveRecord::RecordNotFound,ActiveRecord::RecordInvalid".to_owned(),
+                        "not_found"
+                    ),
+                    (
+                        "Pundit::NotAuthorizedError".to_owned(),
+                        "rescue_from Pundit::NotAuthorizedError"
+                    ),
+                    (
+                        "ActionController::ParameterMissing".to_owned(),
+                        "rescue_from ActionController::ParameterMissing"
+                    ),
+                ]
+            );
+            assert_eq!(controller.methods[1].args, ["e"]);
+            assert_eq!(controller.methods[1].visibility, Visibility::Private);
+            assert_eq!(controller.methods[1].renders.len(), 1);
+        } else {
+            panic!("expected a controller");
+        }
+    }
 
-            // fail to just
-            assert_eq!(false, true);
+    fn visibility_helper(input: &str) -> Vec<(String, Visibility)> {
+        if let Ok(File::Controller(controller)) = parse_class(class_helper(input), "".to_string()) {
+            controller
+                .methods
+                .into_iter()
+                .map(|method| (method.name, method.visibility))
+                .collect()
+        } else {
+            panic!("expected a controller");
         }
     }
+
+    #[test]
+    fn helpers() {
+        let input = "
+        class PostsController < ApplicationController
+            helper :all
+            helper :posts, 'admin/posts'
+            helper FormatHelper
+            helper_method :current_user, :signed_in?
+        end
+        ";
+        if let Ok(File::Controller(controller)) = parse_class(class_helper(input), "".to_string()) {
+            assert_eq!(
+                controller.helpers,
+                ["all", "PostsHelper", "Admin::PostsHelper", "FormatHelper"]
+            );
+            assert_eq!(controller.helper_methods, ["current_user", "signed_in?"]);
+        } else {
+            panic!("expected a controller");
+        }
+    }
+
+    #[test]
+    fn private_section() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+            end
+
+            protected
+
+            def user
+            end
+
+            private
+
+            def user_details
+                User.find(params[:user_id])
+            end
+        end
+        ";
+        assert_eq!(
+            visibility_helper(input),
+            [
+                ("index".to_owned(), Visibility::Public),
+                ("user".to_owned(), Visibility::Protected),
+                ("user_details".to_owned(), Visibility::Private),
+            ]
+        );
+    }
+
+    #[test]
+    fn private_with_names() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+            end
+
+            def user_details
+            end
+
+            def user
+            end
+
+            private :user_details, :user
+            public :user
+        end
+        ";
+        assert_eq!(
+            visibility_helper(input),
+            [
+                ("index".to_owned(), Visibility::Public),
+                ("user_details".to_owned(), Visibility::Private),
+                ("user".to_owned(), Visibility::Public),
+            ]
+        );
+    }
+
+    #[test]
+    fn private_def() {
+        let input = "
+        class PagesController < ApplicationController
+            private def user_details
+                params[:user_id]
+            end
+
+            def index
+            end
+        end
+        ";
+        assert_eq!(
+            visibility_helper(input),
+            [
+                ("user_details".to_owned(), Visibility::Private),
+                ("index".to_owned(), Visibility::Public),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod compute_tests {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{compute, types::AppData};
+
+    fn params(app_data: &AppData, controller: &str) -> Vec<String> {
+        let route = app_data
+            .routes
+            .iter()
+            .find(|x| x.controller == controller)
+            .unwrap();
+        let mut params: Vec<String> = route.get_params(app_data).unwrap().into_iter().collect();
+        params.sort();
+        params
+    }
+
+    #[test]
+    fn namespaced_controllers() {
+        let app_data = compute(Path::new("tests/resources/namespaced_test_case")).unwrap();
+        let route = app_data
+            .routes
+            .iter()
+            .find(|x| x.controller == "api/v1/users_controller")
+            .unwrap();
+
+        assert_eq!(
+            route.get_controller(&app_data).unwrap().name,
+            "Api::V1::UsersController"
+        );
+        // `BaseController` inside `module Api` is `Api::BaseController`
+        assert_eq!(
+            params(&app_data, "api/v1/users_controller"),
+            ["token", "user_id"]
+        );
+        assert_eq!(params(&app_data, "users_controller"), ["id"]);
+        assert_eq!(route.get_view(&app_data).unwrap(), "id,name");
+        assert_eq!(
+            route.get_view_prefixes(&app_data),
+            ["api/v1/users", "api/base", "application"]
+        );
+    }
+
+    #[test]
+    fn namespace_routes() {
+        let app_data = compute(Path::new("tests/resources/namespaced_test_case")).unwrap();
+        let route = app_data
+            .routes
+            .iter()
+            .find(|x| x.uri == "/admin/users")
+            .unwrap();
+
+        assert_eq!(route.controller, "admin/users_controller");
+        assert_eq!(route.get_action(&app_data).unwrap().name, "index");
+        assert_eq!(params(&app_data, "admin/users_controller"), ["role"]);
+    }
 }
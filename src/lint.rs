@@ -0,0 +1,232 @@
+use std::{collections::HashMap, fs};
+
+use crate::{
+    types::{AppData, MethodDetails},
+    utils,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub file: String,
+    // 0 when the file couldn't be read
+    pub line: usize,
+    pub method: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{} {}", self.file, self.line, self.message)
+    }
+}
+
+// anything starting with `_` is unused on purpose
+fn lint_method(method: &MethodDetails) -> Vec<(usize, String)> {
+    let mut results = Vec::new();
+    for (name, count) in &method.local_varaibles {
+        if *count != 0 || name.starts_with('_') {
+            continue;
+        }
+        let offset = method
+            .local_varaible_offsets
+            .get(name)
+            .cloned()
+            .unwrap_or_default();
+        if method
+            .args
+            .iter()
+            .any(|arg| arg.trim_start_matches('*') == name)
+        {
+            // a `rescue_from ... do |e|` block's args are fixed by rails, not chosen
+            if method.name.starts_with("rescue_from ") {
+                continue;
+            }
+            results.push((
+                offset,
+                format!("argument '{}' of '{}' is never used", name, method.name),
+            ));
+        } else {
+            results.push((
+                offset,
+                format!(
+                    "local varaible '{}' in '{}' is assigned but never read",
+                    name, method.name
+                ),
+            ));
+        }
+    }
+
+    results
+}
+
+/// Unused local varaibles and method arguments in every controller, helper and concern
+pub fn lint(app_data: &AppData) -> Vec<Finding> {
+    let mut sources: HashMap<&str, Option<Vec<u8>>> = HashMap::new();
+    let mut findings = Vec::new();
+    let methods = app_data
+        .controllers
+        .values()
+        .flat_map(|x| &x.methods)
+        .chain(app_data.helpers.values().flat_map(|x| &x.methods))
+        .chain(app_data.concerns.values().flat_map(|x| &x.methods));
+
+    for method in methods {
+        let source = sources
+            .entry(&method.file)
+            .or_insert_with(|| fs::read(&method.file).ok());
+        for (offset, message) in lint_method(method) {
+            findings.push(Finding {
+                file: method.file.clone(),
+                line: match source {
+                    Some(source) => utils::line_number(source, offset),
+                    None => 0,
+                },
+                method: method.name.clone(),
+                message,
+            });
+        }
+    }
+
+    findings.sort_by(|a, b| (&a.file, a.line, &a.message).cmp(&(&b.file, b.line, &b.message)));
+    findings
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::lint;
+    use crate::app_data_from_source;
+
+    fn helper(input: &str) -> Vec<String> {
+        lint(&app_data_from_source(&[input]))
+            .into_iter()
+            .map(|x| x.message)
+            .collect()
+    }
+
+    #[test]
+    fn unused_local() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+                a = 1
+                b = 2
+                puts b
+            end
+        end
+        ";
+        assert_eq!(
+            helper(input),
+            ["local varaible 'a' in 'index' is assigned but never read"]
+        );
+    }
+
+    #[test]
+    fn nested_assignment_is_read() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+                if a
+                    if b
+                        x = 1
+                    end
+                end
+                foo(x)
+            end
+        end
+        ";
+        assert_eq!(helper(input), Vec::<String>::new());
+    }
+
+    #[test]
+    fn op_assignment_is_read() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+                count = 0
+                count += 1
+            end
+        end
+        ";
+        assert_eq!(helper(input), Vec::<String>::new());
+    }
+
+    #[test]
+    fn rescue_from_block_argument() {
+        let input = "
+        class PagesController < ApplicationController
+            rescue_from ActiveRecord::RecordNotFound do |e|
+                head :not_found
+            end
+
+            def index
+            end
+        end
+        ";
+        assert_eq!(helper(input), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unused_argument() {
+        let input = "
+        class PagesController < ApplicationController
+            def foobar(details, other, _ignored, *rest, &block)
+                details[:cat]
+            end
+        end
+        ";
+        assert_eq!(
+            helper(input),
+            [
+                "argument 'other' of 'foobar' is never used",
+                "argument 'rest' of 'foobar' is never used"
+            ]
+        );
+    }
+
+    #[test]
+    fn zsuper_uses_arguments() {
+        let input = "
+        class PagesController < ApplicationController
+            def show(id)
+                super
+            end
+        end
+        ";
+        assert_eq!(helper(input), Vec::<String>::new());
+    }
+
+    #[test]
+    fn helpers_and_concerns() {
+        let concern = "
+        module HttpResponses
+            extend ActiveSupport::Concern
+
+            def json_ok(obj, response)
+                render :json => obj
+            end
+        end
+        ";
+        let helper_module = "
+        module PageHelper
+            def blog_category
+                category = params[:cat]
+            end
+        end
+        ";
+        let app_data = app_data_from_source(&[concern, helper_module]);
+        let mut results = lint(&app_data)
+            .into_iter()
+            .map(|x| x.message)
+            .collect::<Vec<String>>();
+        results.sort();
+        assert_eq!(
+            results,
+            [
+                "argument 'response' of 'json_ok' is never used",
+                "local varaible 'category' in 'blog_category' is assigned but never read"
+            ]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use argh::FromArgs;
-use rts::compute;
+use rts::{compute, find_dead_code, lint, FieldType};
 
 fn debug_default() -> bool {
     false
@@ -12,16 +12,75 @@ fn debug_default() -> bool {
 struct RtsCmd {
     /// directory of the ruby on rails project
     #[argh(positional)]
-    root: PathBuf,
+    root: Option<PathBuf>,
 
     /// turn on debug mode
     #[argh(option, default = "debug_default()")]
     debug: bool,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Lint(LintCmd),
+    DeadCode(DeadCodeCmd),
+}
+
+#[derive(FromArgs)]
+/// Report unused local varaibles and method arguments, exits with 1 if any are found
+#[argh(subcommand, name = "lint")]
+struct LintCmd {
+    /// directory of the ruby on rails project
+    #[argh(positional)]
+    root: PathBuf,
+}
+
+#[derive(FromArgs)]
+/// Report helpers, concerns and private controller methods that no route can reach
+#[argh(subcommand, name = "dead-code")]
+struct DeadCodeCmd {
+    /// directory of the ruby on rails project
+    #[argh(positional)]
+    root: PathBuf,
+}
+
+fn run_dead_code(cmd: DeadCodeCmd) -> Result<(), Box<dyn std::error::Error>> {
+    for dead_code in find_dead_code(&compute(&cmd.root)?) {
+        println!("{}", dead_code);
+    }
+
+    Ok(())
+}
+
+fn run_lint(cmd: LintCmd) -> Result<(), Box<dyn std::error::Error>> {
+    let findings = lint(&compute(&cmd.root)?);
+    for finding in &findings {
+        println!("{}", finding);
+    }
+
+    if !findings.is_empty() {
+        println!("{} problems found", findings.len());
+        std::process::exit(1);
+    }
+
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cmd: RtsCmd = argh::from_env();
-    let app_data = compute(&cmd.root)?;
+    match cmd.command {
+        Some(Command::Lint(lint_cmd)) => return run_lint(lint_cmd),
+        Some(Command::DeadCode(dead_code_cmd)) => return run_dead_code(dead_code_cmd),
+        None => {}
+    }
+
+    let root = cmd
+        .root
+        .ok_or("missing directory of the ruby on rails project")?;
+    let app_data = compute(&root)?;
 
     if cmd.debug {
         println!("--- Controllers ---");
@@ -33,8 +92,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             for (kind, action) in &con.actions {
                 println!("#{:?} {}", kind, action);
             }
+            for rescue in &con.rescues {
+                println!(
+                    "#rescue_from {} with {}",
+                    rescue.exceptions.join(", "),
+                    rescue.method
+                );
+            }
             for method in &con.get_own_methods() {
-                println!("- {}", method.name);
+                println!("- {} ({:?})", method.name, method.visibility);
             }
             for method in &con.get_inherited_methods(&app_data) {
                 println!("> {}", method.name);
@@ -42,6 +108,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             for method in &con.get_included_methods(&app_data) {
                 println!("+ {}", method.name);
             }
+            for method in &con.get_action_methods(&app_data) {
+                println!("* {}", method.name);
+            }
             println!();
         }
 
@@ -63,6 +132,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!();
         }
 
+        println!("--- Serializers ---");
+        for serializer in app_data.serializers.values() {
+            println!(
+                "{} < {}\n\t{:?}",
+                serializer.name,
+                serializer.parent,
+                serializer.get_response(&app_data, None, &[])
+            );
+        }
+
         println!("--- Views ---");
         for (controller, value) in &app_data.views {
             for (action, view) in value {
@@ -72,16 +151,91 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     println!("--- Routes ---");
-    for route in app_data.routes.values() {
+    for route in app_data.routes.iter() {
         println!("{}", route);
+        let path_params = route.get_path_params();
+        if !path_params.is_empty() {
+            println!(
+                "@ path = {}",
+                path_params
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
         print!("@ params = ");
         match route.get_params(&app_data) {
             Ok(p) => println!("{:?}", p),
             Err(err) => println!("{}", err),
         }
 
-        if let Ok(p) = route.get_view(&app_data) {
-            println!("Response: {:?}", p)
+        if let Ok(exceptions) = route.get_raised_exceptions(&app_data) {
+            if !exceptions.is_empty() {
+                println!("@ raises = {:?}", exceptions);
+            }
+        }
+
+        if let Ok(content_types) = route.get_content_types(&app_data) {
+            println!("@ produces = {:?}", content_types);
+        }
+
+        if let Ok(responses) = route.get_responses(&app_data) {
+            for response in responses {
+                println!("=> {}", response);
+                if let Some(keys) = route.get_response_keys(&app_data, &response) {
+                    println!("   {}", keys.join(","));
+                }
+                if let Some(view) = route.get_template(&app_data, &response) {
+                    let types = route.get_view_types(&app_data, view);
+                    if types.iter().any(|(_, x)| *x != FieldType::Unknown) {
+                        println!(
+                            "   types {}",
+                            types
+                                .iter()
+                                .map(|(key, x)| format!("{}:{}", key, x))
+                                .collect::<Vec<_>>()
+                                .join(",")
+                        );
+                    }
+                    for conditional in view.conditionals.iter().filter(|x| x.is_one_of()) {
+                        println!("   one of");
+                        for branch in &conditional.branches {
+                            println!("     {}: {}", branch.condition, branch.keys.join(","));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Ok(flow) = route.get_ivar_flow(&app_data) {
+            let mut assigned: Vec<_> = flow.assigned.iter().collect();
+            assigned.sort_by_key(|(name, _)| name.to_owned());
+            for (name, values) in assigned {
+                for value in values {
+                    println!("{} = {}", name, value);
+                }
+            }
+            for name in flow.get_unassigned() {
+                println!("WARNING: view reads {} but nothing assigns it", name);
+            }
+            if let Some(view) = route.find_view(&app_data) {
+                for name in route.get_missing_view_helpers(&app_data, view) {
+                    println!("WARNING: view calls {} but no helper defines it", name);
+                }
+            }
+            if route.find_view(&app_data).is_some() {
+                for name in flow.get_unrendered() {
+                    println!("WARNING: {} is assigned but never rendered", name);
+                }
+            }
+        }
+    }
+
+    if !app_data.mounts.is_empty() {
+        println!("--- Mounts ---");
+        for mount in &app_data.mounts {
+            println!("{}", mount);
         }
     }
 
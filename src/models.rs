@@ -0,0 +1,534 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use convert_case::{Case, Casing};
+use lib_ruby_parser::{
+    nodes::{Block, Class, Send},
+    traverse::Visitor,
+    Node, Parser,
+};
+use walkdir::WalkDir;
+
+use crate::{
+    responses,
+    types::{
+        AppData, Column, FieldType, IvarFlow, Model, ModelAssociation, Receiver, ReturnValue,
+        Table, Value,
+    },
+    utils,
+};
+
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Column { sql_type, null } => {
+                write!(f, "{}{}", sql_type, if *null { "?" } else { "" })
+            }
+            FieldType::Literal { name, null } => {
+                write!(f, "{}{}", name, if *null { "?" } else { "" })
+            }
+            FieldType::Model { name, many, null } => match (many, null) {
+                (true, _) => write!(f, "[{}]", name),
+                (false, true) => write!(f, "{}?", name),
+                (false, false) => write!(f, "{}", name),
+            },
+            FieldType::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+// methods on a model or relation that give back the same model
+const QUERY_METHODS: &[&str] = &[
+    "all",
+    "create",
+    "create!",
+    "distinct",
+    "eager_load",
+    "find",
+    "find_by",
+    "find_by!",
+    "find_or_create_by",
+    "find_or_create_by!",
+    "find_or_initialize_by",
+    "find_sole_by",
+    "first",
+    "first!",
+    "includes",
+    "joins",
+    "last",
+    "last!",
+    "left_joins",
+    "limit",
+    "new",
+    "offset",
+    "order",
+    "preload",
+    "reorder",
+    "select",
+    "sole",
+    "take",
+    "take!",
+    "where",
+];
+
+impl Model {
+    pub fn get_association(&self, name: &str) -> Option<&ModelAssociation> {
+        self.associations.iter().find(|x| x.name == name)
+    }
+}
+
+// the model a value holds e.g. `Comment` for `@post.comments.first` when posts have many comments
+pub fn find_receiver_model(
+    value: &Receiver,
+    flow: &IvarFlow,
+    app_data: &AppData,
+    depth: usize,
+) -> Option<String> {
+    if depth > 5 {
+        return None;
+    }
+    match value {
+        Receiver::Const(name) if app_data.models.contains_key(name) => Some(name.clone()),
+        Receiver::Call(call) => {
+            let model = find_receiver_model(&call.receiver, flow, app_data, depth + 1)?;
+            match app_data.models.get(&model)?.get_association(&call.name) {
+                Some(association) => Some(association.class_name.clone()),
+                None if QUERY_METHODS.contains(&call.name.as_str()) => Some(model),
+                None => None,
+            }
+        }
+        Receiver::Ivar(name) => flow
+            .assigned
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_receiver)
+            .find_map(|x| find_receiver_model(x, flow, app_data, depth + 1)),
+        _ => None,
+    }
+}
+
+/// Follows `path` from `model` through its associations to a column, e.g. `editor`, `name` on
+/// a post is the `name` column of the user the post belongs to. Passing through an optional
+/// association makes everything after it nullable.
+pub fn resolve_field(app_data: &AppData, model: &str, path: &[String]) -> FieldType {
+    let mut current = FieldType::Model {
+        name: model.to_owned(),
+        many: false,
+        null: false,
+    };
+    let mut null = false;
+    for name in path {
+        let model = match &current {
+            FieldType::Model { name, .. } => match app_data.models.get(name) {
+                Some(model) => model,
+                None => return FieldType::Unknown,
+            },
+            _ => return FieldType::Unknown,
+        };
+        current = if let Some(association) = model.get_association(name) {
+            null |= association.optional && !association.many;
+            FieldType::Model {
+                name: association.class_name.clone(),
+                many: association.many,
+                null,
+            }
+        } else if let Some(column) = get_column(app_data, model, name) {
+            FieldType::Column {
+                sql_type: column.sql_type.clone(),
+                null: column.null || null,
+            }
+        } else {
+            return FieldType::Unknown;
+        };
+    }
+    current
+}
+
+/// Types what a helper returns, then follows `path` from it when it is a model. Returning `nil`
+/// in any branch makes the type nullable.
+pub fn resolve_returns(
+    app_data: &AppData,
+    flow: &IvarFlow,
+    returns: &[ReturnValue],
+    path: &[String],
+) -> FieldType {
+    let null = returns
+        .iter()
+        .any(|x| *x == ReturnValue::Literal("null".to_owned()));
+    let field = match returns
+        .iter()
+        .find(|x| **x != ReturnValue::Literal("null".to_owned()))
+    {
+        Some(ReturnValue::Literal(name)) if path.is_empty() => FieldType::Literal {
+            name: name.clone(),
+            null,
+        },
+        Some(ReturnValue::Value(value)) => match find_receiver_model(value, flow, app_data, 0) {
+            Some(model) => resolve_field(app_data, &model, path),
+            None => FieldType::Unknown,
+        },
+        _ => FieldType::Unknown,
+    };
+    match field {
+        FieldType::Model { name, many, .. } if path.is_empty() => {
+            FieldType::Model { name, many, null }
+        }
+        field => field,
+    }
+}
+
+// single table inheritance uses the parent's table
+fn get_column<'a>(app_data: &'a AppData, model: &Model, name: &str) -> Option<&'a Column> {
+    let mut current = model;
+    for _ in 0..5 {
+        if let Some(column) = app_data
+            .tables
+            .get(&current.table_name)
+            .and_then(|x| x.columns.iter().find(|x| x.name == name))
+        {
+            return Some(column);
+        }
+        current = app_data.models.get(&current.parent)?;
+    }
+    None
+}
+
+fn parse_column(send: &Send, table: &mut Table) {
+    let options = responses::options(&send.args);
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| utils::parse_node_str(value))
+    };
+    let null = option("null").as_deref() != Some("false");
+    let names = send
+        .args
+        .iter()
+        .filter(|x| matches!(x, Node::Str(_) | Node::Sym(_)))
+        .map(utils::parse_node_str);
+    match send.method_name.as_str() {
+        "timestamps" => {
+            for name in ["created_at", "updated_at"] {
+                table.columns.push(Column {
+                    name: name.to_owned(),
+                    sql_type: "datetime".to_owned(),
+                    null,
+                });
+            }
+        }
+        "references" | "belongs_to" => {
+            for name in names {
+                if option("polymorphic").as_deref() == Some("true") {
+                    table.columns.push(Column {
+                        name: format!("{}_type", name),
+                        sql_type: "string".to_owned(),
+                        null,
+                    });
+                }
+                table.columns.push(Column {
+                    name: format!("{}_id", name),
+                    sql_type: option("type").unwrap_or_else(|| "bigint".to_owned()),
+                    null,
+                });
+            }
+        }
+        "index" | "check_constraint" => {}
+        sql_type => {
+            for name in names {
+                table.columns.push(Column {
+                    name,
+                    sql_type: sql_type.to_owned(),
+                    null,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchemaFinder {
+    tables: HashMap<String, Table>,
+}
+
+impl Visitor for SchemaFinder {
+    // `create_table "posts", force: :cascade do |t| ... end`
+    fn on_block(&mut self, node: &Block) {
+        match &*node.call {
+            Node::Send(send) if send.method_name == "create_table" && send.recv.is_none() => {
+                let name = match send.args.first() {
+                    Some(name) => utils::parse_node_str(name),
+                    None => return,
+                };
+                let id = responses::options(&send.args)
+                    .iter()
+                    .find(|(x, _)| x == "id")
+                    .map(|(_, value)| utils::parse_node_str(value));
+                let mut table = Table {
+                    name: name.clone(),
+                    columns: Vec::new(),
+                };
+                if id.as_deref() != Some("false") {
+                    table.columns.push(Column {
+                        name: "id".to_owned(),
+                        sql_type: id.unwrap_or_else(|| "bigint".to_owned()),
+                        null: false,
+                    });
+                }
+                let statements = match node.body.as_deref() {
+                    Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+                    Some(node) => vec![node],
+                    None => Vec::new(),
+                };
+                for statement in statements {
+                    if let Node::Send(send) = statement {
+                        parse_column(send, &mut table);
+                    }
+                }
+                self.tables.insert(name, table);
+            }
+            _ => {
+                self.visit(&node.call);
+                self.maybe_visit(&node.body);
+            }
+        }
+    }
+}
+
+pub fn parse_schema(node: &Node) -> HashMap<String, Table> {
+    let mut finder = SchemaFinder::default();
+    finder.visit(node);
+    finder.tables
+}
+
+fn parse_model_class(class: Class, module: &str) -> Model {
+    let name = module.to_owned() + &utils::parse_name(*class.name);
+    let mut model = Model {
+        table_name: utils::pluralize(
+            &name
+                .rsplit("::")
+                .next()
+                .unwrap_or_default()
+                .to_case(Case::Snake),
+        ),
+        name,
+        parent: utils::parse_superclass(class.superclass),
+        associations: Vec::new(),
+    };
+    let statements = match class.body.map(|x| *x) {
+        Some(Node::Begin(begin)) => begin.statements,
+        Some(node) => vec![node],
+        None => Vec::new(),
+    };
+    for statement in statements {
+        match statement {
+            // `self.table_name = "blog_posts"`
+            Node::Send(send) if send.method_name == "table_name=" => {
+                if let Some(name) = send.args.first() {
+                    model.table_name = utils::parse_node_str(name);
+                }
+            }
+            Node::Send(send)
+                if ["belongs_to", "has_one", "has_many"].contains(&send.method_name.as_str()) =>
+            {
+                let name = match send.args.first() {
+                    Some(name) => utils::parse_node_str(name),
+                    None => continue,
+                };
+                let options = responses::options(&send.args);
+                let option = |name: &str| {
+                    options
+                        .iter()
+                        .find(|(x, _)| x == name)
+                        .map(|(_, value)| utils::parse_node_str(value))
+                };
+                let many = send.method_name == "has_many";
+                model.associations.push(ModelAssociation {
+                    class_name: option("class_name").unwrap_or_else(|| {
+                        let name = if many {
+                            utils::singularize(&name)
+                        } else {
+                            name.clone()
+                        };
+                        name.to_case(Case::Pascal)
+                    }),
+                    name,
+                    many,
+                    optional: match send.method_name.as_str() {
+                        "belongs_to" => option("optional").as_deref() == Some("true"),
+                        "has_one" => true,
+                        _ => false,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+    model
+}
+
+fn parse_models(node: Node, module: &str, models: &mut HashMap<String, Model>) {
+    match node {
+        Node::Class(class) => {
+            let model = parse_model_class(class, module);
+            models.insert(model.name.clone(), model);
+        }
+        Node::Module(module_node) => {
+            let module = format!("{}{}::", module, utils::parse_name(*module_node.name));
+            if let Some(body) = module_node.body {
+                parse_models(*body, &module, models);
+            }
+        }
+        Node::Begin(begin) => {
+            for statement in begin.statements {
+                parse_models(statement, module, models);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn parse_schema_file(
+    path: &Path,
+    tables: &mut HashMap<String, Table>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if path.is_file() {
+        if let Some(ast) = Parser::new(&fs::read(path)?, Default::default())
+            .do_parse()
+            .ast
+        {
+            tables.extend(parse_schema(&ast));
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_model_files(
+    path: &Path,
+    models: &mut HashMap<String, Model>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| -> bool { !utils::is_hidden(e) })
+        .filter_map(|e| e.ok())
+    {
+        let f = entry.path();
+        if f.is_file() && f.display().to_string().ends_with(".rb") {
+            let parser = Parser::new(&fs::read(f)?, Default::default()).do_parse();
+            if let Some(ast) = parser.ast {
+                parse_models(ast, "", models);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) fn models_from_source(
+    schema: &str,
+    sources: &[&str],
+) -> (HashMap<String, Table>, HashMap<String, Model>) {
+    let parse = |source: &str| {
+        Parser::new(source.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap()
+    };
+    let mut models = HashMap::new();
+    for source in sources {
+        parse_models(parse(source), "", &mut models);
+    }
+    (parse_schema(&parse(schema)), models)
+}
+
+#[cfg(test)]
+mod models_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::models_from_source;
+    use crate::types::{Column, ModelAssociation};
+
+    #[test]
+    fn schema_and_associations() {
+        let (tables, models) = models_from_source(
+            r#"
+        ActiveRecord::Schema[7.0].define(version: 2023_01_01_000000) do
+          create_table "posts", force: :cascade do |t|
+            t.string "title", null: false
+            t.text "body"
+            t.bigint "editor_id"
+            t.datetime "created_at", null: false
+            t.index ["editor_id"], name: "index_posts_on_editor_id"
+          end
+
+          create_table "categories", id: :uuid do |t|
+            t.string "name"
+          end
+        end
+        "#,
+            &["
+        class Post < ApplicationRecord
+            belongs_to :editor, class_name: 'User', optional: true
+            has_many :categories
+            has_one :cover
+        end
+        "],
+        );
+        assert_eq!(
+            tables["posts"].columns,
+            [
+                Column {
+                    name: "id".to_owned(),
+                    sql_type: "bigint".to_owned(),
+                    null: false
+                },
+                Column {
+                    name: "title".to_owned(),
+                    sql_type: "string".to_owned(),
+                    null: false
+                },
+                Column {
+                    name: "body".to_owned(),
+                    sql_type: "text".to_owned(),
+                    null: true
+                },
+                Column {
+                    name: "editor_id".to_owned(),
+                    sql_type: "bigint".to_owned(),
+                    null: true
+                },
+                Column {
+                    name: "created_at".to_owned(),
+                    sql_type: "datetime".to_owned(),
+                    null: false
+                },
+            ]
+        );
+        assert_eq!(tables["categories"].columns[0].sql_type, "uuid");
+        assert_eq!(models["Post"].table_name, "posts");
+        assert_eq!(
+            models["Post"].associations,
+            [
+                ModelAssociation {
+                    name: "editor".to_owned(),
+                    class_name: "User".to_owned(),
+                    many: false,
+                    optional: true
+                },
+                ModelAssociation {
+                    name: "categories".to_owned(),
+                    class_name: "Category".to_owned(),
+                    many: true,
+                    optional: false
+                },
+                ModelAssociation {
+                    name: "cover".to_owned(),
+                    class_name: "Cover".to_owned(),
+                    many: false,
+                    optional: true
+                },
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use super::types::MethodDetails;
+use super::types::{MethodCall, MethodDetails, Receiver, Response, ReturnValue, Value, Visibility};
+
+use crate::responses;
 
 use crate::utils::{self, parse_node_str};
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -30,6 +32,9 @@ impl std::fmt::Display for MethodDetails {
         if !self.instance_varaibles.is_empty() {
             write!(f, "\n\tinstance vars: {:?}", self.instance_varaibles)?;
         }
+        for (name, value) in &self.ivar_assignments {
+            write!(f, "\n\t{} = {}", name, value)?;
+        }
         if !self.local_varaibles.is_empty() {
             write!(f, "\n\tlocal vars: {:?}", self.local_varaibles)?;
         }
@@ -39,19 +44,191 @@ impl std::fmt::Display for MethodDetails {
                 "\n\tmethod_calls: {}",
                 self.method_calls
                     .iter()
-                    .map(|x| format!("{}({}),", x.0, x.1.join(",")))
+                    .map(|x| format!("{},", x))
                     .collect::<String>()
             )?;
         }
 
         if !self.renders.is_empty() {
-            write!(f, "\n\trender {:?}", self.renders)?;
+            write!(
+                f,
+                "\n\trenders: {}",
+                self.renders
+                    .iter()
+                    .map(|x| format!("{},", x))
+                    .collect::<String>()
+            )?;
+        }
+
+        if self.calls_super {
+            write!(f, "\n\tcalls super")?;
         }
 
         Ok(())
     }
 }
 
+impl std::fmt::Display for Receiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Receiver::ImplicitSelf | Receiver::ExplicitSelf => write!(f, "self"),
+            Receiver::Const(name) | Receiver::Ivar(name) | Receiver::Lvar(name) => {
+                write!(f, "{}", name)
+            }
+            Receiver::Call(call) => write!(f, "{}", call),
+            Receiver::Other(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Receiver(receiver) => write!(f, "{}", receiver),
+            Value::Expression(source) => write!(f, "{}", source),
+            Value::Unknown => write!(f, "(...)"),
+        }
+    }
+}
+
+impl std::fmt::Display for MethodCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.receiver != Receiver::ImplicitSelf {
+            write!(f, "{}.", self.receiver)?;
+        }
+        write!(f, "{}({})", self.name, self.args.join(","))
+    }
+}
+
+pub(crate) fn parse_receiver(recv: Option<&Node>) -> Receiver {
+    match recv {
+        None => Receiver::ImplicitSelf,
+        Some(Node::Self_(_)) => Receiver::ExplicitSelf,
+        Some(node @ Node::Const(_)) => Receiver::Const(utils::parse_node_str(node)),
+        Some(Node::Ivar(ivar)) => Receiver::Ivar(ivar.name.clone()),
+        Some(Node::Lvar(lvar)) => Receiver::Lvar(lvar.name.clone()),
+        Some(Node::Send(send)) => Receiver::Call(Box::new(create_method_call(
+            &send.method_name,
+            send.recv.as_deref(),
+            &send.args,
+        ))),
+        Some(Node::CSend(send)) => Receiver::Call(Box::new(create_method_call(
+            &send.method_name,
+            Some(&send.recv),
+            &send.args,
+        ))),
+        Some(node) => Receiver::Other(utils::parse_node_str(node)),
+    }
+}
+
+fn parse_value(node: Option<&Node>) -> Value {
+    match node.map(|x| parse_receiver(Some(x))) {
+        Some(Receiver::Other(source)) => Value::Expression(source),
+        Some(receiver) => Value::Receiver(receiver),
+        None => Value::Unknown,
+    }
+}
+
+// what a method body gives back from its last expression, both branches of a trailing `if` and
+// the value of a trailing assignment e.g. `@current_user ||= User.find(...)`
+fn parse_returns(node: &Node) -> Vec<ReturnValue> {
+    let literal = |name: &str| vec![ReturnValue::Literal(name.to_owned())];
+    let branches = |if_true: Option<&Node>, if_false: Option<&Node>| {
+        [if_true, if_false]
+            .iter()
+            .flat_map(|x| x.map_or_else(|| literal("null"), parse_returns))
+            .collect()
+    };
+    match node {
+        Node::Begin(nodes::Begin { statements, .. })
+        | Node::KwBegin(nodes::KwBegin { statements, .. }) => statements
+            .last()
+            .map_or_else(|| literal("null"), parse_returns),
+        Node::Return(ret) => ret
+            .args
+            .first()
+            .map_or_else(|| literal("null"), parse_returns),
+        Node::If(stat) => branches(stat.if_true.as_deref(), stat.if_false.as_deref()),
+        Node::IfTernary(stat) => branches(Some(&stat.if_true), Some(&stat.if_false)),
+        Node::OrAsgn(stat) => parse_returns(&stat.value),
+        Node::AndAsgn(stat) => parse_returns(&stat.value),
+        Node::Ivasgn(stat) => stat.value.as_deref().map_or_else(Vec::new, parse_returns),
+        Node::Lvasgn(stat) => stat.value.as_deref().map_or_else(Vec::new, parse_returns),
+        Node::Str(_) | Node::Dstr(_) | Node::Sym(_) | Node::Dsym(_) => literal("string"),
+        Node::Int(_) => literal("integer"),
+        Node::Float(_) => literal("float"),
+        Node::True(_) | Node::False(_) => literal("boolean"),
+        Node::Nil(_) => literal("null"),
+        Node::Array(_) => literal("array"),
+        Node::Hash(_) => literal("object"),
+        node => vec![ReturnValue::Value(parse_receiver(Some(node)))],
+    }
+}
+
+// args parsing is v. bare bones atm
+fn create_method_call(name: &str, recv: Option<&Node>, args: &[Node]) -> MethodCall {
+    MethodCall {
+        name: name.to_owned(),
+        receiver: parse_receiver(recv),
+        args: args.iter().map(utils::parse_node_str).collect(),
+        options: match args.last() {
+            Some(Node::Kwargs(nodes::Kwargs { pairs, .. }))
+            | Some(Node::Hash(nodes::Hash { pairs, .. })) => pairs
+                .iter()
+                .filter_map(|pair| match pair {
+                    Node::Pair(pair) => Some((
+                        utils::parse_node_str(&pair.key),
+                        utils::parse_node_str(&pair.value),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        },
+        arg_values: args.iter().map(responses::parse_json_value).collect(),
+    }
+}
+
+/// Argument names along with where they are defined, splats and block args keep their prefix
+/// e.g. `def foo(a, *b, **c, &d)` gives `a`, `*b`, `**c` and `&d`
+pub fn parse_args(args: &Option<Box<Node>>) -> Vec<(String, usize)> {
+    let mut results = Vec::new();
+    if let Some(args) = args {
+        if let Node::Args(args) = &**args {
+            for arg in &args.args {
+                match arg {
+                    Node::Arg(arg) => results.push((arg.name.clone(), arg.expression_l.begin_pos)),
+                    Node::Optarg(arg) => results.push((arg.name.clone(), arg.name_l.begin_pos)),
+                    Node::Kwarg(arg) => results.push((arg.name.clone(), arg.name_l.begin_pos)),
+                    Node::Kwoptarg(arg) => results.push((arg.name.clone(), arg.name_l.begin_pos)),
+                    Node::Restarg(arg) => results.push((
+                        format!("*{}", arg.name.clone().unwrap_or_default()),
+                        arg.expression_l.begin_pos,
+                    )),
+                    Node::Kwrestarg(arg) => results.push((
+                        format!("**{}", arg.name.clone().unwrap_or_default()),
+                        arg.expression_l.begin_pos,
+                    )),
+                    // the only arg of a block e.g. `do |e|`
+                    Node::Procarg0(arg) => {
+                        for arg in &arg.args {
+                            if let Node::Arg(arg) = arg {
+                                results.push((arg.name.clone(), arg.expression_l.begin_pos))
+                            }
+                        }
+                    }
+                    Node::Blockarg(arg) => {
+                        results.push((format!("&{}", arg.name), arg.expression_l.begin_pos))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    results
+}
+
 fn handle_vector_of_nodes(statements: Vec<Node>, buf: &mut VecDeque<Box<Node>>) {
     for stat in &statements {
         buf.push_back(Box::new(stat.clone()));
@@ -71,58 +248,30 @@ pub fn create_method_details(
     method_name: String,
     args: Vec<String>,
 ) -> MethodDetails {
-    let invalid_method_names = vec![
-        "==",
-        "===",
-        ">",
-        "!=",
-        "<",
-        "+",
-        "-",
-        "*",
-        "%",
-        "where",
-        "find",
-        "find_by",
-        "count",
-        "size",
-        "limit",
-        "reduce",
-        "map",
-        "filter",
-        "first",
-        "last",
-        "empty?",
-        "reject",
-        "in?",
-        "include?",
-        "is_a?",
-        "is_s?",
-        "select",
-        "length",
-        "join",
-        "nil?",
-        "to_s",
-        "joins",
-        "!",
-        "none?",
-        "to_i",
-        "<<",
-        "split",
-        "starts_with",
-        "logger",
-        "params",
-        "to_json",
-        "warn",
-        "touch!",
-    ];
     let mut params = HashSet::new();
     let headers: Vec<(String, String)> = Vec::new();
     let mut instance_varaibles: HashSet<String> = HashSet::new();
-    let mut method_calls: Vec<(String, Vec<String>)> = Vec::new();
+    let mut method_calls: Vec<MethodCall> = Vec::new();
     let mut local_varaibles: HashMap<String, usize> = HashMap::new();
-    let mut renders: Vec<String> = Vec::new();
+    let mut renders: Vec<Response> = Vec::new();
+    // renders already added by their `respond_to` block
+    let mut format_renders: HashSet<usize> = HashSet::new();
+    let mut calls_super = false;
+    let mut ivar_assignments: Vec<(String, Value)> = Vec::new();
+    let mut local_varaible_offsets: HashMap<String, usize> = HashMap::new();
+    let mut local_reads: HashMap<String, usize> = HashMap::new();
+
+    // args are local varaibles as well, apart from `&block` which is normally used through `yield`
+    let local_args: Vec<String> = args
+        .iter()
+        .map(|arg| arg.trim_start_matches('*').to_owned())
+        .filter(|arg| !arg.is_empty() && !arg.starts_with('&'))
+        .collect();
+    for arg in &local_args {
+        local_varaibles.insert(arg.clone(), 0);
+    }
 
+    let returns = parse_returns(&statement);
     let mut buf = VecDeque::new();
 
     buf.push_back(statement);
@@ -144,7 +293,15 @@ pub fn create_method_details(
             Node::Begin(stat) => handle_vector_of_nodes(stat.statements, &mut buf),
 
             // note: ignore optional elements of block here
-            Node::Block(stat) => handle_optional_node(&stat.body, &mut buf),
+            Node::Block(stat) => {
+                if let Some(responses) = responses::parse_respond_to(&stat) {
+                    for (offset, response) in responses {
+                        format_renders.insert(offset);
+                        renders.push(response);
+                    }
+                }
+                handle_optional_node(&stat.body, &mut buf)
+            }
             Node::BlockPass(stat) => buf.push_back(stat.value),
 
             // Node::Case(stat) => {}
@@ -155,7 +312,15 @@ pub fn create_method_details(
 
             Node::ConstPattern(stat) => buf.push_back(stat.pattern),
 
-            Node::CSend(stat) => handle_vector_of_nodes(stat.args, &mut buf),
+            Node::CSend(stat) => {
+                method_calls.push(create_method_call(
+                    &stat.method_name,
+                    Some(&stat.recv),
+                    &stat.args,
+                ));
+                buf.push_back(stat.recv);
+                handle_vector_of_nodes(stat.args, &mut buf)
+            }
 
             // We don't currently use @@var style so not handling it
             // Node::Cvar(stat) => {}
@@ -218,11 +383,14 @@ pub fn create_method_details(
             Node::Index(stat) => {
                 // recv is params
                 // index
-                if let Some(data) = params_index(stat) {
+                if let Some(data) = params_index(stat.clone()) {
                     for item in data {
                         params.insert(item);
                     }
+                } else {
+                    buf.push_back(stat.recv);
                 }
+                handle_vector_of_nodes(stat.indexes, &mut buf)
             }
 
             Node::IndexAsgn(stat) => {
@@ -264,7 +432,8 @@ pub fn create_method_details(
             // TODO: do we want to keep track of instance varaibles?? seems like it is isn't necessary
             // Node::Ivar(stat) => stat.name,
             Node::Ivasgn(stat) => {
-                instance_varaibles.insert(stat.name);
+                instance_varaibles.insert(stat.name.clone());
+                ivar_assignments.push((stat.name, parse_value(stat.value.as_deref())));
 
                 handle_optional_node(&stat.value, &mut buf)
             }
@@ -275,20 +444,9 @@ pub fn create_method_details(
             Node::Kwsplat(stat) => buf.push_back(stat.value),
 
             Node::Lvar(stat) => {
-                // parser already handles local varaible assignment and access
-                // therefore we can keep track of it
-                // verbose due to: avoiding mutable_borrow_reservation_conflict see: https://github.com/rust-lang/rust/issues/59159#
-                let mut var_exists = false;
-                let v = if let Some(value) = local_varaibles.get(&stat.name) {
-                    var_exists = true;
-                    *value
-                } else {
-                    0
-                };
-
-                if var_exists {
-                    local_varaibles.insert(stat.name, v + 1);
-                }
+                // the parser only makes an `Lvar` for a name it has seen assigned earlier in the
+                // source, so every read counts even though the queue isn't in source order
+                *local_reads.entry(stat.name).or_default() += 1;
             }
 
             // specail case for headers and payload!!!!
@@ -298,6 +456,11 @@ pub fn create_method_details(
                 } else {
                     0
                 };
+                // the queue isn't in source order so keep the earliest assignment
+                let offset = local_varaible_offsets
+                    .entry(stat.name.clone())
+                    .or_insert(stat.name_l.begin_pos);
+                *offset = (*offset).min(stat.name_l.begin_pos);
                 local_varaibles.insert(stat.name, v);
                 handle_optional_node(&stat.value, &mut buf)
             }
@@ -333,6 +496,10 @@ pub fn create_method_details(
             Node::Numblock(stat) => buf.push_back(stat.body),
 
             Node::OpAsgn(stat) => {
+                // `x += 1` reads `x` before assigning it
+                if let Node::Lvasgn(lvar) = &*stat.recv {
+                    *local_reads.entry(lvar.name.clone()).or_default() += 1;
+                }
                 buf.push_back(stat.recv);
                 buf.push_back(stat.value)
             }
@@ -343,7 +510,13 @@ pub fn create_method_details(
                 buf.push_back(stat.rhs);
             }
             Node::OrAsgn(stat) => {
-                buf.push_back(stat.recv);
+                // memoized e.g. `@user ||= User.find(params[:id])`
+                if let Node::Ivasgn(ivar) = *stat.recv {
+                    instance_varaibles.insert(ivar.name.clone());
+                    ivar_assignments.push((ivar.name, parse_value(Some(&stat.value))));
+                } else {
+                    buf.push_back(stat.recv);
+                }
                 buf.push_back(stat.value)
             }
 
@@ -383,6 +556,12 @@ pub fn create_method_details(
                     }
                 }
                 SendTypes::ParamsRequire => {
+                    // kept as a call since it raises when the key is missing
+                    method_calls.push(create_method_call(
+                        &stat.method_name,
+                        stat.recv.as_deref(),
+                        &stat.args,
+                    ));
                     for value in stat.args {
                         params.insert(utils::parse_node_str(&value));
                     }
@@ -393,6 +572,11 @@ pub fn create_method_details(
                     }
                     if let Some(recv) = stat.recv {
                         if let Node::Send(stat) = *recv {
+                            method_calls.push(create_method_call(
+                                &stat.method_name,
+                                stat.recv.as_deref(),
+                                &stat.args,
+                            ));
                             for value in stat.args {
                                 params.insert(utils::parse_node_str(&value));
                             }
@@ -400,14 +584,17 @@ pub fn create_method_details(
                     }
                 }
                 _ => {
-                    if stat.method_name == "render" {
-                        renders.push(stat.args.iter().map(|x| utils::parse_node_str(x)).collect())
-                    } else if !invalid_method_names.iter().any(|x| x == &stat.method_name) {
-                        // we don't need to store every method call, specailly when even `1 == 1` is a method call as such....
-                        // args parsing is v. bare bones atm
-                        method_calls.push((
-                            stat.method_name,
-                            stat.args.iter().map(|x| utils::parse_node_str(x)).collect(),
+                    if let Some(response) = responses::parse_response(&stat) {
+                        if !format_renders.contains(&stat.expression_l.begin_pos) {
+                            renders.push(response)
+                        }
+                    } else {
+                        // every call is kept along with its receiver, `1 == 1` included,
+                        // use `MethodCall::is_local` to find the ones we can resolve
+                        method_calls.push(create_method_call(
+                            &stat.method_name,
+                            stat.recv.as_deref(),
+                            &stat.args,
                         ));
                     }
                     handle_vector_of_nodes(stat.args, &mut buf);
@@ -417,6 +604,11 @@ pub fn create_method_details(
 
             Node::Splat(stat) => handle_optional_node(&stat.value, &mut buf),
 
+            Node::Super(stat) => {
+                calls_super = true;
+                handle_vector_of_nodes(stat.args, &mut buf)
+            }
+
             Node::Undef(stat) => handle_vector_of_nodes(stat.names, &mut buf),
             Node::UnlessGuard(stat) => buf.push_back(stat.cond),
             Node::Until(stat) => {
@@ -444,18 +636,38 @@ pub fn create_method_details(
 
             Node::Yield(stat) => handle_vector_of_nodes(stat.args, &mut buf),
 
+            // `super` without args passes along the same args
+            Node::ZSuper(_) => {
+                calls_super = true;
+                for arg in &local_args {
+                    if let Some(count) = local_varaibles.get_mut(arg) {
+                        *count += 1;
+                    }
+                }
+            }
+
             _ => {}
         }
     }
+    for (name, count) in local_varaibles.iter_mut() {
+        *count += local_reads.get(name).copied().unwrap_or(0);
+    }
     MethodDetails {
         name: method_name,
         args,
+        visibility: Visibility::Public,
+        class_method: false,
         params,
         headers,
         instance_varaibles,
+        ivar_assignments,
         method_calls,
         renders,
+        returns,
         local_varaibles,
+        local_varaible_offsets,
+        calls_super,
+        file: String::new(),
     }
 }
 
@@ -522,11 +734,7 @@ fn params_index(stat: Index) -> Option<Vec<String>> {
     let mut buf = VecDeque::new();
     buf.push_back(*stat.recv.clone());
     let mut depth = 0;
-    let mut data: Vec<String> = stat
-        .indexes
-        .iter()
-        .map(|x| utils::parse_node_str(x))
-        .collect();
+    let mut data: Vec<String> = stat.indexes.iter().map(utils::parse_node_str).collect();
     while let Some(temp) = buf.pop_front() {
         depth += 1;
         match temp {
@@ -568,6 +776,7 @@ mod params_tests {
     use pretty_assertions::assert_eq;
 
     use crate::params::{parse_send, SendTypes};
+    use crate::types::{JsonValue, MethodCall, Receiver, Value};
 
     use super::create_method_details;
 
@@ -594,7 +803,7 @@ mod params_tests {
         .into_iter()
         .collect::<Vec<String>>();
         results.sort();
-        return results.join(", ");
+        results.join(", ")
     }
 
     #[test]
@@ -753,13 +962,13 @@ mod params_tests {
             let mut results = create_method_details(temp, "".to_string(), Vec::new())
                 .method_calls
                 .into_iter()
-                .collect::<Vec<(String, Vec<String>)>>();
+                .collect::<Vec<MethodCall>>();
             // results.sort();
-            results.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-            return results
+            results.sort_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
+            results
                 .iter()
-                .map(|x| format!("{}({})", x.0, x.1.join(",")))
-                .collect();
+                .map(|x| format!("{}({})", x.name, x.args.join(",")))
+                .collect()
         }
         #[test]
         fn method_call_single_argument() {
@@ -798,6 +1007,100 @@ mod params_tests {
         fn method_call_not_render_for_template() {
             assert_eq!(method_call_helper("render 'show'"), "");
         }
+
+        fn local_method_call_helper(input: &str) -> String {
+            create_method_details(helper(input), "".to_string(), Vec::new())
+                .method_calls
+                .iter()
+                .filter(|x| x.is_local())
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        }
+
+        #[test]
+        fn method_call_chain_is_not_local() {
+            assert_eq!(local_method_call_helper("User.where().foobar()"), "");
+        }
+
+        #[test]
+        fn method_call_local_count() {
+            assert_eq!(local_method_call_helper("count + 1"), "count()");
+        }
+
+        #[test]
+        fn method_call_explicit_self() {
+            assert_eq!(local_method_call_helper("self.foo(1)"), "self.foo(1)");
+        }
+
+        #[test]
+        fn method_call_operators_are_not_local() {
+            assert_eq!(local_method_call_helper("a = 1\na == 2"), "");
+        }
+
+        #[test]
+        fn method_call_receiver_chain() {
+            let calls = create_method_details(
+                helper("User.where(page: 1).foobar()"),
+                "".to_string(),
+                Vec::new(),
+            )
+            .method_calls;
+            let foobar = calls.iter().find(|x| x.name == "foobar").unwrap();
+            assert_eq!(
+                foobar.receiver,
+                Receiver::Call(Box::new(MethodCall {
+                    name: "where".to_owned(),
+                    receiver: Receiver::Const("User".to_owned()),
+                    args: vec!["page=>1".to_owned()],
+                    options: vec![("page".to_owned(), "1".to_owned())],
+                    arg_values: vec![JsonValue::Object(vec![(
+                        "page".to_owned(),
+                        JsonValue::Value(Receiver::Other("1".to_owned()))
+                    )])],
+                }))
+            );
+            assert_eq!(foobar.to_string(), "User.where(page=>1).foobar()");
+        }
+
+        #[test]
+        fn method_call_safe_navigation() {
+            assert_eq!(
+                create_method_details(helper("@user&.name"), "".to_string(), Vec::new())
+                    .method_calls[0]
+                    .to_string(),
+                "@user.name()"
+            );
+        }
+    }
+
+    #[test]
+    fn ivar_assignments() {
+        let input = "
+            @data = Post.find(params[:id])
+            @user ||= current_user
+            @page = params[:page]
+            @first, @last = names
+        ";
+        let assignments =
+            create_method_details(helper(input), "".to_string(), Vec::new()).ivar_assignments;
+        let results = assignments
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            results,
+            [
+                "@data = Post.find(params[id])",
+                "@user = current_user()",
+                "@page = params[page]",
+                "@first = (...)",
+                "@last = (...)"
+            ]
+        );
+        assert!(matches!(assignments[0].1, Value::Receiver(_)));
+        assert!(matches!(assignments[2].1, Value::Expression(_)));
     }
 
     #[test]
@@ -0,0 +1,729 @@
+use crate::params::parse_receiver;
+use crate::types::{
+    JsonValue, MethodCall, MethodDetails, Receiver, Response, ResponseBody, Status,
+};
+use crate::utils;
+
+use lib_ruby_parser::{
+    nodes::{Block, Hash, Kwargs, Send},
+    traverse::Visitor,
+    Node,
+};
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Code(code) => write!(f, "{}", code),
+            Status::Dynamic(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseBody::Json(value) => write!(f, "json {}", value),
+            ResponseBody::Xml(value) => write!(f, "xml {}", value),
+            ResponseBody::Plain(value) => write!(f, "plain {}", value),
+            ResponseBody::Html(value) => write!(f, "html {}", value),
+            ResponseBody::Template(name) if name.is_empty() => write!(f, "template"),
+            ResponseBody::Template(name) => write!(f, "template {}", name),
+            ResponseBody::Redirect(location) => write!(f, "redirect {}", location),
+            ResponseBody::Nothing => write!(f, "nothing"),
+        }
+    }
+}
+
+impl std::fmt::Display for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.status,
+            self.content_type.as_deref().unwrap_or("-"),
+            self.body
+        )?;
+        if let Some(format) = &self.format {
+            write!(f, " [{}]", format)?;
+        }
+        if let Some(exception) = &self.exception {
+            write!(f, " rescues {}", exception)?;
+        }
+        if let Some(exception) = &self.unrescued {
+            write!(f, " unrescued {}", exception)?;
+        }
+        Ok(())
+    }
+}
+
+// rails renders the action's own template with a 200 unless told otherwise
+impl Default for Response {
+    fn default() -> Self {
+        Response {
+            status: Status::Code(200),
+            content_type: None,
+            body: ResponseBody::Template(String::new()),
+            format: None,
+            exception: None,
+            unrescued: None,
+            serializer: None,
+            shape: None,
+        }
+    }
+}
+
+/// Maps the symbols rails accepts for `status:` to their code e.g. `:not_found` is 404
+pub fn status_code(symbol: &str) -> Option<u16> {
+    Some(match symbol {
+        "continue" => 100,
+        "switching_protocols" => 101,
+        "processing" => 102,
+        "early_hints" => 103,
+        "ok" | "success" => 200,
+        "created" => 201,
+        "accepted" => 202,
+        "non_authoritative_information" => 203,
+        "no_content" => 204,
+        "reset_content" => 205,
+        "partial_content" => 206,
+        "multi_status" => 207,
+        "already_reported" => 208,
+        "im_used" => 226,
+        "multiple_choices" => 300,
+        "moved_permanently" => 301,
+        "found" | "redirect" => 302,
+        "see_other" => 303,
+        "not_modified" => 304,
+        "use_proxy" => 305,
+        "temporary_redirect" => 307,
+        "permanent_redirect" => 308,
+        "bad_request" => 400,
+        "unauthorized" => 401,
+        "payment_required" => 402,
+        "forbidden" => 403,
+        "not_found" | "missing" => 404,
+        "method_not_allowed" => 405,
+        "not_acceptable" => 406,
+        "proxy_authentication_required" => 407,
+        "request_timeout" => 408,
+        "conflict" => 409,
+        "gone" => 410,
+        "length_required" => 411,
+        "precondition_failed" => 412,
+        "payload_too_large" | "request_entity_too_large" => 413,
+        "uri_too_long" | "request_uri_too_long" => 414,
+        "unsupported_media_type" => 415,
+        "range_not_satisfiable" | "requested_range_not_satisfiable" => 416,
+        "expectation_failed" => 417,
+        "misdirected_request" => 421,
+        "unprocessable_entity" | "unprocessable_content" => 422,
+        "locked" => 423,
+        "failed_dependency" => 424,
+        "too_early" => 425,
+        "upgrade_required" => 426,
+        "precondition_required" => 428,
+        "too_many_requests" => 429,
+        "request_header_fields_too_large" => 431,
+        "unavailable_for_legal_reasons" => 451,
+        "internal_server_error" | "error" => 500,
+        "not_implemented" => 501,
+        "bad_gateway" => 502,
+        "service_unavailable" => 503,
+        "gateway_timeout" => 504,
+        "http_version_not_supported" => 505,
+        "variant_also_negotiates" => 506,
+        "insufficient_storage" => 507,
+        "loop_detected" => 508,
+        "bandwidth_limit_exceeded" => 509,
+        "not_extended" => 510,
+        "network_authentication_required" => 511,
+        _ => return None,
+    })
+}
+
+pub fn parse_status(node: &Node) -> Status {
+    match node {
+        Node::Sym(sym) => {
+            let name = sym.name.to_string_lossy();
+            status_code(&name).map_or(Status::Dynamic(name), Status::Code)
+        }
+        Node::Int(_) | Node::Str(_) => {
+            let value = utils::parse_node_str(node);
+            value.parse().map_or(Status::Dynamic(value), Status::Code)
+        }
+        node => Status::Dynamic(parse_receiver(Some(node)).to_string()),
+    }
+}
+
+// `key: value` and `:key => value` pairs of the trailing options hash
+pub(crate) fn options(args: &[Node]) -> Vec<(String, &Node)> {
+    let pairs = match args.last() {
+        Some(Node::Kwargs(kwargs)) => &kwargs.pairs,
+        Some(Node::Hash(hash)) => &hash.pairs,
+        _ => return Vec::new(),
+    };
+    pairs
+        .iter()
+        .filter_map(|pair| match pair {
+            Node::Pair(pair) => Some((utils::parse_node_str(&pair.key), &*pair.value)),
+            _ => None,
+        })
+        .collect()
+}
+
+// partials are templates starting with `_` e.g. `posts/row` is `posts/_row`
+fn partial_template(name: &str) -> String {
+    match name.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/_{}", dir, name),
+        None => format!("_{}", name),
+    }
+}
+
+fn parse_render(args: &[Node]) -> Response {
+    let options = options(args);
+    let option = |name: &str| options.iter().find(|(key, _)| key == name).map(|x| x.1);
+    let value = |node: &Node| parse_receiver(Some(node));
+
+    let (body, content_type) = if let Some(node) = option("json") {
+        (ResponseBody::Json(value(node)), Some("application/json"))
+    } else if let Some(node) = option("xml") {
+        (ResponseBody::Xml(value(node)), Some("application/xml"))
+    } else if let Some(node) = option("plain").or_else(|| option("body")) {
+        (ResponseBody::Plain(value(node)), Some("text/plain"))
+    } else if let Some(node) = option("html").or_else(|| option("inline")) {
+        (ResponseBody::Html(value(node)), Some("text/html"))
+    } else if let Some(node) = option("template").or_else(|| option("action")) {
+        (ResponseBody::Template(utils::parse_node_str(node)), None)
+    } else if let Some(node) = option("partial") {
+        (
+            ResponseBody::Template(partial_template(&utils::parse_node_str(node))),
+            None,
+        )
+    } else if option("nothing").is_some() {
+        (ResponseBody::Nothing, None)
+    } else {
+        match args.first() {
+            Some(node @ (Node::Sym(_) | Node::Str(_))) => {
+                (ResponseBody::Template(utils::parse_node_str(node)), None)
+            }
+            _ => (ResponseBody::Template(String::new()), None),
+        }
+    };
+
+    Response {
+        status: option("status").map_or(Status::Code(200), parse_status),
+        content_type: option("content_type")
+            .map(utils::parse_node_str)
+            .or_else(|| content_type.map(|x| x.to_owned())),
+        body,
+        shape: option("json")
+            .map(parse_json_value)
+            .filter(|x| !matches!(x, JsonValue::Value(_))),
+        serializer: option("serializer")
+            .or_else(|| option("each_serializer"))
+            .map(utils::parse_node_str),
+        ..Default::default()
+    }
+}
+
+/// The response for `render`, `head`, `redirect_to` and `redirect_back` sent to self,
+/// anything else is `None`
+pub fn parse_response(send: &Send) -> Option<Response> {
+    if send.recv.is_some() {
+        return None;
+    }
+
+    match send.method_name.as_str() {
+        "render" => Some(parse_render(&send.args)),
+        "head" => Some(Response {
+            status: send.args.first().map_or(Status::Code(200), parse_status),
+            body: ResponseBody::Nothing,
+            ..Default::default()
+        }),
+        "redirect_to" | "redirect_back" => {
+            let options = options(&send.args);
+            let option = |name: &str| options.iter().find(|(key, _)| key == name).map(|x| x.1);
+            let location = match send.args.first() {
+                Some(Node::Kwargs(_)) | None => option("fallback_location"),
+                Some(node) => Some(node),
+            };
+            Some(Response {
+                status: option("status").map_or(Status::Code(302), parse_status),
+                content_type: Some("text/html".to_owned()),
+                body: ResponseBody::Redirect(
+                    location.map_or(Receiver::Other("back".to_owned()), |x| {
+                        parse_receiver(Some(x))
+                    }),
+                ),
+                ..Default::default()
+            })
+        }
+        _ => None,
+    }
+}
+
+// `:id`, `[:id, :email]` and `{ profile: { only: [:bio] } }` style options
+fn parse_names(node: &Node) -> Vec<(String, Option<&Node>)> {
+    match node {
+        Node::Array(array) => array.elements.iter().flat_map(parse_names).collect(),
+        Node::Hash(hash) => hash
+            .pairs
+            .iter()
+            .filter_map(|pair| match pair {
+                Node::Pair(pair) => Some((utils::parse_node_str(&pair.key), Some(&*pair.value))),
+                _ => None,
+            })
+            .collect(),
+        node => vec![(utils::parse_node_str(node), None)],
+    }
+}
+
+// the `only:`, `except:`, `methods:` and `include:` options of `as_json`
+fn parse_record(value: Receiver, options: Option<&Node>) -> JsonValue {
+    let pairs = match options {
+        Some(Node::Kwargs(kwargs)) => &kwargs.pairs[..],
+        Some(Node::Hash(hash)) => &hash.pairs[..],
+        _ => &[],
+    };
+    let mut only = None;
+    let mut except = Vec::new();
+    let mut extra = Vec::new();
+    for pair in pairs {
+        if let Node::Pair(pair) = pair {
+            let names = parse_names(&pair.value);
+            match utils::parse_node_str(&pair.key).as_str() {
+                "only" => only = Some(names.into_iter().map(|x| x.0).collect()),
+                "except" => except.extend(names.into_iter().map(|x| x.0)),
+                "methods" => {
+                    for (name, _) in names {
+                        extra.push((name, JsonValue::Value(value.clone())));
+                    }
+                }
+                "include" => {
+                    for (name, nested) in names {
+                        let nested = parse_record(Receiver::Other(name.clone()), nested);
+                        extra.push((name, nested));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    JsonValue::Record {
+        value,
+        only,
+        except,
+        extra,
+    }
+}
+
+/// What a value looks like once rendered as json, hash and array literals, `.map` blocks and
+/// `as_json`/`to_json` options are followed and anything else is a `JsonValue::Value`
+pub fn parse_json_value(node: &Node) -> JsonValue {
+    match node {
+        Node::Hash(Hash { pairs, .. }) | Node::Kwargs(Kwargs { pairs, .. }) => JsonValue::Object(
+            pairs
+                .iter()
+                .filter_map(|pair| match pair {
+                    Node::Pair(pair) => Some((
+                        utils::parse_node_str(&pair.key),
+                        parse_json_value(&pair.value),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        Node::Array(array) => JsonValue::Array(Box::new(array.elements.first().map_or(
+            JsonValue::Value(Receiver::Other("[]".to_owned())),
+            parse_json_value,
+        ))),
+        Node::Block(block) => match (&*block.call, block.body.as_deref()) {
+            (Node::Send(send), Some(body))
+                if ["map", "collect"].contains(&send.method_name.as_str()) =>
+            {
+                let last = match body {
+                    Node::Begin(begin) => begin.statements.last().unwrap_or(body),
+                    body => body,
+                };
+                JsonValue::Array(Box::new(parse_json_value(last)))
+            }
+            _ => JsonValue::Value(parse_receiver(Some(node))),
+        },
+        Node::Send(send)
+            if ["as_json", "to_json", "serializable_hash"].contains(&send.method_name.as_str()) =>
+        {
+            match send.recv.as_deref() {
+                Some(recv @ (Node::Hash(_) | Node::Array(_) | Node::Block(_))) => {
+                    parse_json_value(recv)
+                }
+                recv => parse_record(parse_receiver(recv), send.args.first()),
+            }
+        }
+        node => JsonValue::Value(parse_receiver(Some(node))),
+    }
+}
+
+/// Keys of a json value in the same format views use e.g. `items.name`
+pub fn flatten_json_value(value: &JsonValue) -> Vec<String> {
+    let nested = |name: &str, value: &JsonValue| -> Vec<String> {
+        match flatten_json_value(value) {
+            keys if keys.is_empty() => vec![name.to_owned()],
+            keys => keys.iter().map(|x| format!("{}.{}", name, x)).collect(),
+        }
+    };
+    match value {
+        JsonValue::Object(pairs) => pairs
+            .iter()
+            .flat_map(|(name, value)| nested(name, value))
+            .collect(),
+        JsonValue::Array(value) => flatten_json_value(value),
+        JsonValue::Record {
+            only,
+            except,
+            extra,
+            ..
+        } => only
+            .iter()
+            .flatten()
+            .filter(|x| !except.contains(x))
+            .cloned()
+            .chain(extra.iter().flat_map(|(name, value)| match value {
+                JsonValue::Value(_) => vec![name.clone()],
+                value => nested(name, value),
+            }))
+            .collect(),
+        JsonValue::Value(_) => Vec::new(),
+    }
+}
+
+/// A response from a method that renders one of its args e.g. `def json_ok(obj, status)`, as
+/// sent by one call to it
+pub fn bind_args(response: &Response, method: &MethodDetails, call: &MethodCall) -> Response {
+    let arg = |name: &str| {
+        let index = method.args.iter().position(|x| x == name)?;
+        Some((call.args.get(index)?, call.arg_values.get(index)?))
+    };
+    let mut response = response.clone();
+    if let Status::Dynamic(name) = &response.status {
+        if let Some((value, _)) = arg(name) {
+            response.status = value
+                .parse()
+                .ok()
+                .or_else(|| status_code(value))
+                .map_or(Status::Dynamic(value.clone()), Status::Code);
+        }
+    }
+    if let ResponseBody::Json(Receiver::Lvar(name)) = &response.body {
+        if let Some((source, value)) = arg(name) {
+            response.body = ResponseBody::Json(match value {
+                JsonValue::Value(receiver)
+                | JsonValue::Record {
+                    value: receiver, ..
+                } => receiver.clone(),
+                _ => Receiver::Other(source.clone()),
+            });
+            response.shape = Some(value.clone()).filter(|x| !matches!(x, JsonValue::Value(_)));
+        }
+    }
+
+    response
+}
+
+// content type rails sends for a `respond_to` format
+fn format_content_type(format: &str) -> Option<&'static str> {
+    Some(match format {
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "html" => "text/html",
+        "text" => "text/plain",
+        "js" => "text/javascript",
+        "csv" => "text/csv",
+        "pdf" => "application/pdf",
+        "rss" => "application/rss+xml",
+        "atom" => "application/atom+xml",
+        _ => return None,
+    })
+}
+
+#[derive(Default)]
+struct RenderFinder {
+    responses: Vec<(usize, Response)>,
+}
+
+impl Visitor for RenderFinder {
+    fn on_send(&mut self, node: &Send) {
+        if let Some(response) = parse_response(node) {
+            self.responses.push((node.expression_l.begin_pos, response));
+        }
+        self.maybe_visit(&node.recv);
+        self.visit_all(&node.args);
+    }
+}
+
+/// Responses for each format of a `respond_to do |format| ... end` block along with where
+/// each render starts, a format without a render gets the action's template in that format
+pub fn parse_respond_to(block: &Block) -> Option<Vec<(usize, Response)>> {
+    match &*block.call {
+        Node::Send(send) if send.recv.is_none() && send.method_name == "respond_to" => {}
+        _ => return None,
+    }
+    let format_arg = match block.args.as_deref() {
+        Some(Node::Args(args)) => match args.args.first() {
+            Some(Node::Arg(arg)) => arg.name.clone(),
+            Some(Node::Procarg0(arg)) => match arg.args.first() {
+                Some(Node::Arg(arg)) => arg.name.clone(),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let is_format = |recv: &Option<Box<Node>>| matches!(recv.as_deref(), Some(Node::Lvar(lvar)) if lvar.name == format_arg);
+
+    let statements = match block.body.as_deref() {
+        Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+        Some(node) => vec![node],
+        None => Vec::new(),
+    };
+    let mut results = Vec::new();
+    for statement in statements {
+        let (send, body) = match statement {
+            Node::Send(send) => (send, None),
+            Node::Block(block) => match &*block.call {
+                Node::Send(send) => (send, block.body.as_deref()),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if !is_format(&send.recv) {
+            continue;
+        }
+
+        let mut finder = RenderFinder::default();
+        if let Some(body) = body {
+            finder.visit(body);
+        }
+        if finder.responses.is_empty() {
+            finder
+                .responses
+                .push((send.expression_l.begin_pos, Response::default()));
+        }
+        for (offset, mut response) in finder.responses {
+            if let ResponseBody::Template(_) = response.body {
+                if response.content_type.is_none() {
+                    response.content_type =
+                        format_content_type(&send.method_name).map(|x| x.to_owned());
+                }
+            }
+            response.format = Some(send.method_name.clone());
+            results.push((offset, response));
+        }
+    }
+
+    Some(results)
+}
+
+/// The exception a well known call raises e.g. `Post.find(params[:id])` raises
+/// `ActiveRecord::RecordNotFound` and `params.require(:post)` raises
+/// `ActionController::ParameterMissing`
+pub fn raised_exception(call: &MethodCall) -> Option<&'static str> {
+    let on_params = matches!(
+        &call.receiver,
+        Receiver::Call(recv) if recv.name == "params" && recv.receiver == Receiver::ImplicitSelf
+    );
+    let on_model = matches!(call.receiver, Receiver::Const(_) | Receiver::Call(_)) && !on_params;
+    Some(match call.name.as_str() {
+        // `find` with a block is `Enumerable#find`
+        "find" if on_model && !call.args.is_empty() => "ActiveRecord::RecordNotFound",
+        "find_by!" | "first!" | "last!" | "take!" | "sole" | "find_sole_by" if !call.is_local() => {
+            "ActiveRecord::RecordNotFound"
+        }
+        "require" if on_params => "ActionController::ParameterMissing",
+        "save!" | "create!" | "update!" | "update_attributes!" if !call.is_local() => {
+            "ActiveRecord::RecordInvalid"
+        }
+        "destroy!" if !call.is_local() => "ActiveRecord::RecordNotDestroyed",
+        "authorize" if call.is_local() => "Pundit::NotAuthorizedError",
+        _ => return None,
+    })
+}
+
+/// Status rails responds with when nothing rescues the exception, `Pundit::NotAuthorizedError`
+/// gets the 403 the pundit readme sets up
+pub fn default_exception_status(exception: &str) -> u16 {
+    match exception {
+        "ActionController::RoutingError"
+        | "AbstractController::ActionNotFound"
+        | "ActiveRecord::RecordNotFound" => 404,
+        "ActionController::MethodNotAllowed" | "ActionController::UnknownHttpMethod" => 405,
+        "ActionController::UnknownFormat" => 406,
+        "ActiveRecord::StaleObjectError" => 409,
+        "ActionController::BadRequest"
+        | "ActionController::ParameterMissing"
+        | "ActionDispatch::Http::Parameters::ParseError" => 400,
+        "ActionController::InvalidAuthenticityToken"
+        | "ActionController::InvalidCrossOriginRequest"
+        | "ActiveRecord::RecordInvalid"
+        | "ActiveRecord::RecordNotSaved" => 422,
+        "ActionController::NotImplemented" => 501,
+        "Pundit::NotAuthorizedError" => 403,
+        _ => 500,
+    }
+}
+
+/// Whether `rescue_from handled` catches the exception, either by name or one of the
+/// superclasses of the exceptions `raised_exception` knows about
+pub fn is_rescued_by(exception: &str, handled: &str) -> bool {
+    let ancestors: &[&str] = match exception {
+        "ActiveRecord::RecordNotFound"
+        | "ActiveRecord::RecordInvalid"
+        | "ActiveRecord::RecordNotDestroyed" => &["ActiveRecord::ActiveRecordError"],
+        "ActionController::ParameterMissing" => &["KeyError", "IndexError"],
+        "Pundit::NotAuthorizedError" => &["Pundit::Error"],
+        _ => &[],
+    };
+    let handled = handled.trim_start_matches("::");
+    handled == exception
+        || ancestors.contains(&handled)
+        || handled == "StandardError"
+        || handled == "Exception"
+}
+
+#[cfg(test)]
+mod responses_tests {
+    use super::{flatten_json_value, parse_json_value, parse_respond_to, parse_response};
+    use lib_ruby_parser::{Node, Parser};
+
+    fn response_helper(input: &str) -> String {
+        let result = Parser::new(input.as_bytes(), Default::default()).do_parse();
+        match result.ast {
+            Some(Node::Send(send)) => {
+                parse_response(&send).map_or("none".to_owned(), |x| x.to_string())
+            }
+            _ => "not a send".to_owned(),
+        }
+    }
+
+    #[test]
+    fn render_json() {
+        assert_eq!(
+            response_helper("render json: @user, status: :created"),
+            "201 application/json json @user"
+        );
+        assert_eq!(
+            response_helper("render :status => @status, :json => @error"),
+            "@status application/json json @error"
+        );
+    }
+
+    #[test]
+    fn render_template() {
+        assert_eq!(response_helper("render :show"), "200 - template show");
+        assert_eq!(response_helper("render 'show'"), "200 - template show");
+        assert_eq!(
+            response_helper("render template: 'posts/show', status: 422"),
+            "422 - template posts/show"
+        );
+        assert_eq!(
+            response_helper("render status: :not_found"),
+            "404 - template"
+        );
+        assert_eq!(
+            response_helper("render partial: 'row'"),
+            "200 - template _row"
+        );
+        assert_eq!(
+            response_helper("render partial: 'posts/row', locals: { post: @post }"),
+            "200 - template posts/_row"
+        );
+    }
+
+    #[test]
+    fn render_plain_and_nothing() {
+        assert_eq!(
+            response_helper("render plain: 'OK', status: :accepted"),
+            "202 text/plain plain OK"
+        );
+        assert_eq!(response_helper("render nothing: true"), "200 - nothing");
+    }
+
+    #[test]
+    fn head_and_redirect() {
+        assert_eq!(response_helper("head :no_content"), "204 - nothing");
+        assert_eq!(response_helper("head 418"), "418 - nothing");
+        assert_eq!(
+            response_helper("redirect_to root_path"),
+            "302 text/html redirect root_path()"
+        );
+        assert_eq!(
+            response_helper("redirect_to @post, status: :see_other"),
+            "303 text/html redirect @post"
+        );
+    }
+
+    #[test]
+    fn other_calls() {
+        assert_eq!(response_helper("foo.render json: 1"), "none");
+        assert_eq!(response_helper("respond json: 1"), "none");
+    }
+
+    #[test]
+    fn respond_to() {
+        let input = "
+        respond_to do |format|
+            format.html
+            format.json { render json: @user.errors, status: :unprocessable_entity }
+            format.xml { head :no_content }
+        end
+        ";
+        let result = Parser::new(input.as_bytes(), Default::default()).do_parse();
+        let responses = match result.ast {
+            Some(Node::Block(block)) => parse_respond_to(&block).unwrap(),
+            _ => panic!("not a block"),
+        };
+        assert_eq!(
+            responses
+                .iter()
+                .map(|x| x.1.to_string())
+                .collect::<Vec<String>>(),
+            [
+                "200 text/html template [html]",
+                "422 application/json json @user.errors() [json]",
+                "204 - nothing [xml]"
+            ]
+        );
+    }
+
+    fn json_keys_helper(input: &str) -> Vec<String> {
+        let result = Parser::new(input.as_bytes(), Default::default()).do_parse();
+        flatten_json_value(&parse_json_value(&result.ast.unwrap()))
+    }
+
+    #[test]
+    fn hash_literals() {
+        assert_eq!(
+            json_keys_helper("{ id: @x.id, items: @x.items.map { |i| { name: i.name } } }"),
+            ["id", "items.name"]
+        );
+        assert_eq!(
+            json_keys_helper("[{ 'total' => 1, meta: { page: 1 } }]"),
+            ["total", "meta.page"]
+        );
+    }
+
+    #[test]
+    fn as_json_options() {
+        assert_eq!(
+            json_keys_helper(
+                "@user.as_json(only: [:id, :email, :name], except: :name, methods: :avatar_url, include: { profile: { only: :bio } })"
+            ),
+            ["id", "email", "avatar_url", "profile.bio"]
+        );
+        assert_eq!(
+            json_keys_helper("@user.to_json(only: :id, include: :team)"),
+            ["id", "team"]
+        );
+        assert!(json_keys_helper("@user").is_empty());
+    }
+}
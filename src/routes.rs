@@ -1,9 +1,15 @@
-use crate::types::AppData;
+use crate::types::{
+    AppData, Controller, FieldType, IvarFlow, MethodCall, MethodDetails, MethodOwner, MethodRef,
+    Receiver, Response, ResponseBody, Serializer, Status, Value, View, Visibility,
+};
+use crate::utils;
+use crate::{models, responses};
 use convert_case::{Case, Casing};
-use std::collections::HashSet;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum RequestMethod {
     Get,
     Post,
@@ -11,6 +17,9 @@ pub enum RequestMethod {
     Put,
     Patch,
     Options,
+    Head,
+    Connect,
+    Trace,
 }
 
 impl FromStr for RequestMethod {
@@ -24,80 +33,949 @@ impl FromStr for RequestMethod {
             "PUT" => RequestMethod::Put,
             "PATCH" => RequestMethod::Patch,
             "OPTIONS" => RequestMethod::Options,
+            "HEAD" => RequestMethod::Head,
+            "CONNECT" => RequestMethod::Connect,
+            "TRACE" => RequestMethod::Trace,
             _ => return Err(format!("unknown Request method '{}'", &(*s).to_owned())),
         })
     }
 }
 
+// `GET|POST` from `match 'x', via: [:get, :post]`
+fn parse_methods(s: &str) -> Result<Vec<RequestMethod>, String> {
+    s.split('|').map(RequestMethod::from_str).collect()
+}
+
+// a piece of a route pattern, `/posts(/:page)` is `/posts` then an optional `/` and `:page`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Segment {
+    Static(String),
+    Dynamic {
+        name: String,
+        pattern: Option<String>,
+    },
+    Glob {
+        name: String,
+        pattern: Option<String>,
+    },
+    Optional(Vec<Segment>),
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Static(text) => write!(f, "{}", text),
+            Segment::Dynamic { name, .. } => write!(f, ":{}", name),
+            Segment::Glob { name, .. } => write!(f, "*{}", name),
+            Segment::Optional(segments) => write!(
+                f,
+                "({})",
+                segments.iter().map(|x| x.to_string()).collect::<String>()
+            ),
+        }
+    }
+}
+
+fn parse_segment_list(chars: &mut std::iter::Peekable<std::str::Chars>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => segments.push(Segment::Optional(parse_segment_list(chars))),
+            ')' => break,
+            ':' | '*' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|x| x.is_alphanumeric() || *x == '_') {
+                    name.push(c);
+                }
+                segments.push(if c == ':' {
+                    Segment::Dynamic {
+                        name,
+                        pattern: None,
+                    }
+                } else {
+                    Segment::Glob {
+                        name,
+                        pattern: None,
+                    }
+                });
+            }
+            c => match segments.last_mut() {
+                Some(Segment::Static(text)) => text.push(c),
+                _ => segments.push(Segment::Static(c.to_string())),
+            },
+        }
+    }
+    segments
+}
+
+/// Splits a route pattern like `/files/*path` or `/posts(/:page)` into its segments
+pub fn parse_segments(uri: &str) -> Vec<Segment> {
+    parse_segment_list(&mut uri.chars().peekable())
+}
+
+// `constraints: { id: /\d+/ }` onto the `:id` segment
+pub fn apply_constraints(segments: &mut [Segment], constraints: &[(String, String)]) {
+    for segment in segments {
+        match segment {
+            Segment::Dynamic { name, pattern } | Segment::Glob { name, pattern } => {
+                if let Some((_, regex)) = constraints.iter().find(|(key, _)| key == name) {
+                    *pattern = Some(regex.clone());
+                }
+            }
+            Segment::Optional(segments) => apply_constraints(segments, constraints),
+            Segment::Static(_) => (),
+        }
+    }
+}
+
+// every path an optional group allows, shortest first
+fn expand_segments(segments: &[Segment]) -> Vec<String> {
+    let mut paths = vec![String::new()];
+    for segment in segments {
+        paths = match segment {
+            Segment::Optional(inner) => {
+                let inner = expand_segments(inner);
+                paths
+                    .iter()
+                    .flat_map(|path| {
+                        std::iter::once(path.clone())
+                            .chain(inner.iter().map(move |x| path.clone() + x))
+                    })
+                    .collect()
+            }
+            segment => paths
+                .into_iter()
+                .map(|x| x + &segment.to_string())
+                .collect(),
+        };
+    }
+    paths
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PathParam {
+    pub name: String,
+    pub optional: bool,
+    pub glob: bool,
+    pub pattern: Option<String>,
+}
+
+impl std::fmt::Display for PathParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            if self.glob { "*" } else { "" },
+            self.name,
+            if self.optional { "?" } else { "" }
+        )?;
+        if let Some(pattern) = &self.pattern {
+            write!(f, " /{}/", pattern)?;
+        }
+        Ok(())
+    }
+}
+
+fn collect_path_params(segments: &[Segment], optional: bool, params: &mut Vec<PathParam>) {
+    for segment in segments {
+        match segment {
+            Segment::Dynamic { name, pattern } | Segment::Glob { name, pattern } => {
+                params.push(PathParam {
+                    name: name.clone(),
+                    optional,
+                    glob: matches!(segment, Segment::Glob { .. }),
+                    pattern: pattern.clone(),
+                })
+            }
+            Segment::Optional(segments) => collect_path_params(segments, true, params),
+            Segment::Static(_) => (),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Request {
-    pub method: RequestMethod,
+    // empty for `via: :all` which matches any method
+    pub methods: Vec<RequestMethod>,
     pub prefix: String,
+    // the route pattern without `(.:format)`, parsed into `segments`
     pub uri: String,
+    pub segments: Vec<Segment>,
     pub controller: String,
     pub action: String,
 }
 
 impl std::fmt::Display for Request {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?} {}", self.method, self.uri)
+        if self.methods.is_empty() {
+            write!(f, "Any {}", self.uri)
+        } else {
+            let methods: Vec<String> = self.methods.iter().map(|x| format!("{:?}", x)).collect();
+            write!(f, "{} {}", methods.join("|"), self.uri)
+        }
+    }
+}
+
+// a rack app mounted with `mount Sidekiq::Web => '/sidekiq'`, its routes are opaque to us
+#[derive(Debug, PartialEq)]
+pub struct Mount {
+    pub app: String,
+    pub path: String,
+    pub name: String,
+}
+
+impl std::fmt::Display for Mount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.app, self.path)
     }
 }
 
 impl Request {
-    pub fn get_params(&self, app_data: &AppData) -> Result<HashSet<String>, String> {
-        if let Some(controller) = app_data
+    pub fn get_path_params(&self) -> Vec<PathParam> {
+        let mut params = Vec::new();
+        collect_path_params(&self.segments, false, &mut params);
+        params
+    }
+
+    /// The concrete paths of the route, one for each way its optional groups can be filled
+    pub fn expand_paths(&self) -> Vec<String> {
+        expand_segments(&self.segments)
+    }
+
+    /// The controller's class name e.g. `Api::V1::UsersController` for `api/v1/users`
+    pub fn get_controller_name(&self) -> String {
+        self.controller
+            .split('/')
+            .map(|x| x.to_case(Case::Pascal))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    pub fn get_controller<'a>(&self, app_data: &'a AppData) -> Result<&'a Controller, String> {
+        app_data
             .controllers
-            .get(&self.controller.to_case(Case::Pascal))
-        {
-            let mut params: HashSet<String>;
-            // handle action
-            if let Some(method) = controller.get_method_by_name(&self.action, app_data) {
-                params = controller.get_method_params(&method, app_data);
+            .get(&self.get_controller_name())
+            .ok_or_else(|| {
+                format!(
+                    "ERROR: controller {} not found for request {}",
+                    self.get_controller_name(),
+                    self.uri
+                )
+            })
+    }
+
+    // rails only routes to public methods, anything else is a 404
+    pub fn get_action<'a>(&self, app_data: &'a AppData) -> Result<&'a MethodDetails, String> {
+        let controller = self.get_controller(app_data)?;
+        match controller.get_method_by_name(&self.action, app_data) {
+            Some(method) if method.visibility == Visibility::Public => Ok(method),
+            Some(method) => Err(format!(
+                "ERROR: action {} in controller {} for request {} is {:?} so it can't be routed to",
+                self.action,
+                self.get_controller_name(),
+                self.uri,
+                method.visibility
+            )),
+            None => Err(format!(
+                "ERROR: action {} not found in controller {} for request {}",
+                self.action,
+                self.get_controller_name(),
+                self.uri
+            )),
+        }
+    }
+
+    pub fn get_params(&self, app_data: &AppData) -> Result<HashSet<String>, String> {
+        let controller = self.get_controller(app_data)?;
+        self.get_action(app_data)?;
+        // handle action
+        let mut params = controller
+            .get_method_params(&self.action, app_data)
+            .unwrap_or_default();
+        // handle before/after/rescue
+        for (_, action_name) in controller.get_callbacks(app_data) {
+            if let Some(method_params) = controller.get_method_params(action_name, app_data) {
+                params.extend(method_params);
             } else {
                 return Err(format!(
                     "ERROR: action {} not found in controller {} for request {}",
-                    self.action,
-                    &self.controller.to_case(Case::Pascal),
+                    action_name,
+                    self.get_controller_name(),
                     self.uri
                 ));
             }
-            // handle before/after/rescue
-            for (_, action_name) in &controller.actions {
-                if let Some(method) = controller.get_method_by_name(action_name, app_data) {
-                    params.extend(controller.get_method_params(&method, app_data));
-                } else {
-                    return Err(format!(
-                        "ERROR: action {} not found in controller {} for request {}",
-                        self.action,
-                        &self.controller.to_case(Case::Pascal),
-                        self.uri
-                    ));
+        }
+
+        Ok(params)
+    }
+
+    /// The view directories rails searches for the controller's templates, its own and then each
+    /// parent controller's e.g. `api/v1/users`, `api/base`, `application`
+    pub fn get_view_prefixes(&self, app_data: &AppData) -> Vec<String> {
+        let mut prefixes = vec![self.controller.trim_end_matches("_controller").to_owned()];
+        let mut current = self.get_controller(app_data).ok();
+        while let Some(con) = current {
+            if con.parent.is_empty() || !con.parent.ends_with("Controller") {
+                break;
+            }
+            let parent = con.get_parent(app_data);
+            let prefix = controller_path(parent.map_or(&con.parent, |x| &x.name));
+            if prefixes.contains(&prefix) {
+                break;
+            }
+            prefixes.push(prefix);
+            current = parent;
+        }
+        prefixes
+    }
+
+    /// Resolves a template name the way `render` does, `posts/show` from the views root and
+    /// `show` from the controller's prefixes, ignoring any format e.g. `show.json`
+    pub fn find_template<'a>(&self, app_data: &'a AppData, name: &str) -> Option<&'a View> {
+        let name = name.trim_start_matches('/');
+        let name = match name.rsplit_once('/') {
+            Some((dir, action)) => {
+                format!("{}/{}", dir, action.split('.').next().unwrap_or_default())
+            }
+            None => name.split('.').next().unwrap_or_default().to_owned(),
+        };
+        match name.rsplit_once('/') {
+            Some((dir, action)) => app_data.views.get(dir)?.get(action),
+            None => self
+                .get_view_prefixes(app_data)
+                .iter()
+                .find_map(|prefix| app_data.views.get(prefix)?.get(&name)),
+        }
+    }
+
+    pub fn find_view<'a>(&self, app_data: &'a AppData) -> Option<&'a View> {
+        self.find_template(app_data, &self.action)
+    }
+
+    pub fn get_view(&self, app_data: &AppData) -> Result<String, String> {
+        match self.find_view(app_data) {
+            Some(view) => Ok(view.response.join(",")),
+            None => Err("not found".to_string()),
+        }
+    }
+
+    // methods reachable from the action and optionally its callbacks
+    fn get_reachable(
+        &self,
+        app_data: &AppData,
+        with_callbacks: bool,
+    ) -> Result<HashSet<MethodRef>, String> {
+        let controller = self.get_controller(app_data)?;
+        let table = controller
+            .get_method_table(app_data)
+            .ok_or_else(|| format!("ERROR: no methods found for {}", controller.name))?;
+        let mut reachable = HashSet::new();
+        let callbacks = if with_callbacks {
+            controller.get_callbacks(app_data)
+        } else {
+            Vec::new()
+        };
+        let roots = std::iter::once(&self.action).chain(callbacks.into_iter().map(|x| &x.1));
+        for root in roots {
+            if let Some(method) = table.get(root) {
+                reachable.extend(table.get_reachable(method, app_data));
+            }
+        }
+
+        Ok(reachable)
+    }
+
+    /// Every response the action, its callbacks or anything they call can send, when the action
+    /// never renders it falls back to its own template with a 200. Responses from the
+    /// controller's `rescue_from` handlers are included along with the exceptions they rescue.
+    pub fn get_responses(&self, app_data: &AppData) -> Result<Vec<Response>, String> {
+        self.get_action(app_data)?;
+        let renders = |reachable: HashSet<MethodRef>| -> Vec<Response> {
+            let methods: Vec<&MethodDetails> = reachable
+                .iter()
+                .filter_map(|x| app_data.get_method(x))
+                .collect();
+            let mut results = Vec::new();
+            for method in &methods {
+                // methods like `json_ok(obj, status)` render whatever they are called with
+                let calls: Vec<&MethodCall> = methods
+                    .iter()
+                    .flat_map(|x| &x.method_calls)
+                    .filter(|x| x.is_local() && x.name == method.name)
+                    .collect();
+                for response in &method.renders {
+                    if method.args.is_empty() || calls.is_empty() {
+                        results.push(response.clone());
+                    } else {
+                        for call in &calls {
+                            results.push(responses::bind_args(response, method, call));
+                        }
+                    }
                 }
             }
+            results
+        };
 
-            Ok(params)
+        let mut responses = renders(self.get_reachable(app_data, true)?);
+        if renders(self.get_reachable(app_data, false)?).is_empty() {
+            responses.push(Response::default());
+        }
+
+        let controller = self.get_controller(app_data)?;
+        if let Some(table) = controller.get_method_table(app_data) {
+            for handler in controller.get_rescue_handlers(app_data) {
+                match table.get(&handler.method) {
+                    Some(method) => {
+                        for mut response in renders(table.get_reachable(method, app_data)) {
+                            response.exception = Some(handler.exceptions.join(", "));
+                            responses.push(response);
+                        }
+                    }
+                    None => println!(
+                        "WARNING: rescue_from handler {} not found in {}",
+                        handler.method, controller.name
+                    ),
+                }
+            }
+        }
+
+        // anything raised that no handler rescues gets rails' default status
+        let handled: Vec<&String> = controller
+            .get_rescue_handlers(app_data)
+            .into_iter()
+            .flat_map(|x| &x.exceptions)
+            .collect();
+        for exception in self.get_raised_exceptions(app_data)? {
+            if !handled
+                .iter()
+                .any(|x| responses::is_rescued_by(&exception, x))
+            {
+                responses.push(Response {
+                    status: Status::Code(responses::default_exception_status(&exception)),
+                    body: ResponseBody::Nothing,
+                    unrescued: Some(exception),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // jbuilder and jb views are always json
+        for response in &mut responses {
+            if response.content_type.is_none() && self.get_template(app_data, response).is_some() {
+                response.content_type = Some("application/json".to_owned());
+            }
+        }
+
+        let mut results: Vec<Response> = Vec::new();
+        for response in responses {
+            if !results.contains(&response) {
+                results.push(response);
+            }
+        }
+        results.sort_by_key(|x| x.to_string());
+
+        Ok(results)
+    }
+
+    /// Exceptions the action, its callbacks or anything they call are likely to raise
+    pub fn get_raised_exceptions(&self, app_data: &AppData) -> Result<Vec<String>, String> {
+        let mut exceptions: Vec<String> = self
+            .get_reachable(app_data, true)?
+            .iter()
+            .filter_map(|x| app_data.get_method(x))
+            .flat_map(|x| &x.method_calls)
+            .filter_map(responses::raised_exception)
+            .map(|x| x.to_owned())
+            .collect();
+        exceptions.sort();
+        exceptions.dedup();
+
+        Ok(exceptions)
+    }
+
+    /// Every content type the endpoint can produce
+    pub fn get_content_types(&self, app_data: &AppData) -> Result<Vec<String>, String> {
+        let mut content_types: Vec<String> = self
+            .get_responses(app_data)?
+            .into_iter()
+            .filter_map(|x| x.content_type)
+            .collect();
+        content_types.sort();
+        content_types.dedup();
+
+        Ok(content_types)
+    }
+
+    /// The jbuilder view a response renders, `render :edit` looks in the controller's and its
+    /// parents' views and `render 'posts/show'` in posts. Only json and unspecified formats use them.
+    pub fn get_template<'a>(&self, app_data: &'a AppData, response: &Response) -> Option<&'a View> {
+        let name = match &response.body {
+            ResponseBody::Template(name) => name,
+            _ => return None,
+        };
+        if !matches!(response.format.as_deref(), None | Some("json")) {
+            return None;
+        }
+        if name.is_empty() {
+            self.find_view(app_data)
         } else {
-            Err(format!(
-                "ERROR: action {} not found in controller {} for request {}",
-                self.action,
-                &self.controller.to_case(Case::Pascal),
-                self.uri
-            ))
+            self.find_template(app_data, name)
         }
     }
 
-    pub fn get_view(&self, app_data: &AppData) -> Result<String, String> {
-        if let Some(actions) = app_data
-            .views
-            .get(self.controller.trim_end_matches("_controller"))
+    /// Types a view's keys by following their values back to a model assigned by the action,
+    /// e.g. `json.(@post, :title)` with `@post = Post.find(...)` is the posts title column, or
+    /// to what the helper they call returns
+    pub fn get_view_types(&self, app_data: &AppData, view: &View) -> Vec<(String, FieldType)> {
+        let flow = self.get_ivar_flow(app_data).ok();
+        view.response
+            .iter()
+            .map(|key| {
+                let field = view
+                    .sources
+                    .get(&key.replace('?', ""))
+                    .zip(flow.as_ref())
+                    .and_then(|(source, flow)| {
+                        if !source.root.starts_with('@') {
+                            let helper = self.find_view_helper(app_data, &source.root)?;
+                            return Some(models::resolve_returns(
+                                app_data,
+                                flow,
+                                &app_data.get_method(&helper)?.returns,
+                                &source.path,
+                            ));
+                        }
+                        let model = models::find_receiver_model(
+                            &Receiver::Ivar(source.root.clone()),
+                            flow,
+                            app_data,
+                            0,
+                        )?;
+                        Some(models::resolve_field(app_data, &model, &source.path))
+                    })
+                    .unwrap_or(FieldType::Unknown);
+                (key.clone(), field)
+            })
+            .collect()
+    }
+
+    // the controller and its superclasses that were parsed
+    fn get_controllers<'a>(&self, app_data: &'a AppData) -> Vec<&'a Controller> {
+        let mut controllers: Vec<&Controller> = Vec::new();
+        let mut current = self.get_controller(app_data).ok();
+        while let Some(con) = current {
+            if controllers.iter().any(|x| x.name == con.name) {
+                break;
+            }
+            controllers.push(con);
+            current = con.get_parent(app_data);
+        }
+        controllers
+    }
+
+    /// Helper modules the controller's views can call: any `helper` declarations, each
+    /// controller's own e.g. `PagesHelper` for `PagesController` and `ApplicationHelper`, or
+    /// every helper with `helper :all`
+    pub fn get_view_helpers(&self, app_data: &AppData) -> Vec<String> {
+        let controllers = self.get_controllers(app_data);
+        let mut names = Vec::new();
+        if controllers
+            .iter()
+            .any(|x| x.helpers.iter().any(|x| x == "all"))
         {
-            if let Some(view) = actions.get(&self.action) {
-                return Ok(view.response.join(","));
+            names.extend(app_data.helpers.keys().cloned());
+            names.sort();
+            return names;
+        }
+        for con in controllers {
+            names.extend(con.helpers.iter().cloned());
+            names.push(format!("{}Helper", con.name.trim_end_matches("Controller")));
+        }
+        names.push("ApplicationHelper".to_owned());
+        let mut seen = HashSet::new();
+        names
+            .into_iter()
+            .filter(|x| app_data.helpers.contains_key(x) && seen.insert(x.clone()))
+            .collect()
+    }
+
+    /// A method the controller's views can call, from a helper module or a `helper_method`
+    pub fn find_view_helper(&self, app_data: &AppData, name: &str) -> Option<MethodRef> {
+        for helper in self.get_view_helpers(app_data) {
+            if let Some(index) = app_data.helpers[&helper]
+                .methods
+                .iter()
+                .position(|x| x.name == name)
+            {
+                return Some(MethodRef {
+                    owner: MethodOwner::Helper(helper),
+                    index,
+                });
             }
         }
-        Err("not found".to_string())
+        let controller = self.get_controller(app_data).ok()?;
+        if self
+            .get_controllers(app_data)
+            .iter()
+            .any(|x| x.helper_methods.iter().any(|x| x == name))
+        {
+            return controller.get_method_table(app_data)?.get(name).cloned();
+        }
+        None
+    }
+
+    /// Methods a view calls that no helper defines, leaving out rails' own view helpers and
+    /// `_url`/`_path` route helpers
+    pub fn get_missing_view_helpers(&self, app_data: &AppData, view: &View) -> Vec<String> {
+        view.helper_calls
+            .iter()
+            .filter(|x| {
+                !RAILS_VIEW_HELPERS.contains(&x.as_str())
+                    && !x.ends_with("_url")
+                    && !x.ends_with("_path")
+                    && self.find_view_helper(app_data, x).is_none()
+            })
+            .cloned()
+            .collect()
     }
+
+    /// The serializer `render json:` uses, either the one it is given, one it calls e.g.
+    /// `UserSerializer.new(@user).serializable_hash` or `UserBlueprint.render(@user)`, or the
+    /// model's by convention e.g. `render json: @user` uses `UserSerializer` when
+    /// `@user = User.find(...)`
+    pub fn get_serializer<'a>(
+        &self,
+        app_data: &'a AppData,
+        response: &Response,
+    ) -> Option<&'a Serializer> {
+        if let Some(name) = &response.serializer {
+            return app_data.serializers.get(name);
+        }
+        let value = match &response.body {
+            ResponseBody::Json(value) => value,
+            _ => return None,
+        };
+        let flow = self.get_ivar_flow(app_data).ok()?;
+        if let Some(call) = find_serializer_call(value, &flow, app_data, 0) {
+            if let Receiver::Const(name) = &call.receiver {
+                return app_data.serializers.get(name);
+            }
+        }
+        let model = find_model(value, &flow, 0)?;
+        app_data.serializers.get(&format!("{}Serializer", model))
+    }
+
+    /// Keys the response sends, from its view or serializer
+    pub fn get_response_keys(
+        &self,
+        app_data: &AppData,
+        response: &Response,
+    ) -> Option<Vec<String>> {
+        if let Some(view) = self.get_template(app_data, response) {
+            return Some(view.response.clone());
+        }
+        if let Some(shape) = &response.shape {
+            let keys = responses::flatten_json_value(shape);
+            if !keys.is_empty() {
+                return Some(keys);
+            }
+        }
+        let serializer = self.get_serializer(app_data, response)?;
+
+        // `view:` and `include:` given to the serializer
+        let flow = self.get_ivar_flow(app_data).ok()?;
+        let call = match &response.body {
+            ResponseBody::Json(value) => find_serializer_call(value, &flow, app_data, 0),
+            _ => None,
+        };
+        let view = call.and_then(|x| x.get_option("view"));
+        let include: Vec<String> = call
+            .and_then(|x| x.get_option("include"))
+            .map(|x| {
+                x.trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(|x| x.to_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(serializer.get_response(app_data, view, &include))
+    }
+
+    /// Instance varaibles assigned by the action, its callbacks and anything they call
+    /// along with the ones the view reads
+    pub fn get_ivar_flow(&self, app_data: &AppData) -> Result<IvarFlow, String> {
+        let reachable = self.get_reachable(app_data, true)?;
+        let mut flow = IvarFlow::default();
+        for details in reachable.iter().filter_map(|x| app_data.get_method(x)) {
+            for (name, value) in &details.ivar_assignments {
+                flow.assigned
+                    .entry(name.clone())
+                    .or_default()
+                    .push(value.clone());
+            }
+        }
+
+        if let Some(view) = self.find_view(app_data) {
+            flow.read = view.instance_varaibles.clone();
+        }
+
+        Ok(flow)
+    }
+}
+
+// view helpers that come with rails, along with ruby's own methods
+const RAILS_VIEW_HELPERS: &[&str] = &[
+    "action_name",
+    "controller",
+    "controller_name",
+    "cookies",
+    "dom_id",
+    "flash",
+    "l",
+    "lambda",
+    "localize",
+    "number_to_currency",
+    "number_to_human",
+    "number_to_human_size",
+    "number_to_percentage",
+    "number_with_delimiter",
+    "number_with_precision",
+    "params",
+    "pluralize",
+    "polymorphic_path",
+    "polymorphic_url",
+    "proc",
+    "raise",
+    "raw",
+    "request",
+    "sanitize",
+    "session",
+    "simple_format",
+    "strip_tags",
+    "t",
+    "time_ago_in_words",
+    "translate",
+    "truncate",
+    "url_for",
+];
+
+// `Api::BaseController` is `api/base`
+fn controller_path(name: &str) -> String {
+    name.trim_end_matches("Controller")
+        .split("::")
+        .map(|x| x.to_case(Case::Snake))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// `UserSerializer.new(...)` or `UserBlueprint.render(...)` somewhere in the value
+fn find_serializer_call<'a>(
+    value: &'a Receiver,
+    flow: &'a IvarFlow,
+    app_data: &AppData,
+    depth: usize,
+) -> Option<&'a MethodCall> {
+    if depth > 5 {
+        return None;
+    }
+    match value {
+        Receiver::Call(call) => match &call.receiver {
+            Receiver::Const(name)
+                if app_data.serializers.contains_key(name)
+                    && ["new", "render", "render_as_hash", "render_as_json"]
+                        .contains(&call.name.as_str()) =>
+            {
+                Some(call)
+            }
+            receiver => find_serializer_call(receiver, flow, app_data, depth + 1),
+        },
+        Receiver::Ivar(name) => flow
+            .assigned
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_receiver)
+            .find_map(|x| find_serializer_call(x, flow, app_data, depth + 1)),
+        _ => None,
+    }
+}
+
+// the model a value most likely holds e.g. `User` for `User.where(...).first`, falling back on
+// the varaible's name when nothing says otherwise
+fn find_model(value: &Receiver, flow: &IvarFlow, depth: usize) -> Option<String> {
+    if depth > 5 {
+        return None;
+    }
+    match value {
+        Receiver::Const(name) => Some(name.clone()),
+        Receiver::Call(call) => find_model(&call.receiver, flow, depth + 1),
+        Receiver::Ivar(name) => flow
+            .assigned
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_receiver)
+            .find_map(|x| find_model(x, flow, depth + 1))
+            .or_else(|| {
+                Some(utils::singularize(name.trim_start_matches('@')).to_case(Case::Pascal))
+            }),
+        Receiver::Lvar(name) => Some(utils::singularize(name).to_case(Case::Pascal)),
+        _ => None,
+    }
+}
+
+// the regex rails' router compiles a route to, `(.:format)` is stripped from our uris so it
+// is added back as an optional extension
+// a bare `*` has no name to capture it as
+fn capture_group(name: &str, pattern: &str) -> String {
+    if name.is_empty() {
+        format!("(?:{})", pattern)
+    } else {
+        format!("(?P<{}>{})", name, pattern)
+    }
+}
+
+fn segments_regex(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Static(text) => regex::escape(text),
+            Segment::Dynamic { name, pattern } => capture_group(
+                name,
+                &pattern
+                    .as_deref()
+                    .map_or("[^/.?]+".to_owned(), |x| format!("(?:{})", x)),
+            ),
+            Segment::Glob { name, pattern } => capture_group(
+                name,
+                &pattern
+                    .as_deref()
+                    .map_or(".+?".to_owned(), |x| format!("(?:{})", x)),
+            ),
+            Segment::Optional(segments) => format!("(?:{})?", segments_regex(segments)),
+        })
+        .collect()
+}
+
+fn compile_route(request: &Request) -> Option<Regex> {
+    let format = if request.get_path_params().iter().any(|x| x.name == "format") {
+        ""
+    } else {
+        r"(?:\.(?P<format>[^/.?]+))?"
+    };
+    let regex = format!(r"\A{}{}\z", segments_regex(&request.segments), format);
+    match Regex::new(&regex) {
+        Ok(regex) => Some(regex),
+        Err(_) => {
+            println!("WARNING: could not compile the constraints of {}", request);
+            None
+        }
+    }
+}
+
+/// A request matched by `RouteTable::recognize` with the values of its path params
+#[derive(Debug, PartialEq)]
+pub struct RouteMatch<'a> {
+    pub request: &'a Request,
+    pub params: HashMap<String, String>,
+}
+
+/// The routes in the order they are declared, like rails the first route that matches wins
+#[derive(Debug, Default)]
+pub struct RouteTable {
+    routes: Vec<(Request, Option<Regex>)>,
+}
+
+impl RouteTable {
+    pub fn new(routes: Vec<Request>) -> Self {
+        let mut table = RouteTable::default();
+        for request in routes {
+            table.push(request);
+        }
+        table
+    }
+
+    pub fn push(&mut self, request: Request) {
+        let regex = compile_route(&request);
+        self.routes.push((request, regex));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Request> {
+        self.routes.iter().map(|(request, _)| request)
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    fn find(&self, method: &RequestMethod, path: &str) -> Option<RouteMatch<'_>> {
+        self.routes.iter().find_map(|(request, regex)| {
+            if !request.methods.is_empty() && !request.methods.contains(method) {
+                return None;
+            }
+            let captures = regex.as_ref()?.captures(path)?;
+            let params = regex
+                .as_ref()?
+                .capture_names()
+                .flatten()
+                .filter_map(|name| {
+                    Some((name.to_owned(), captures.name(name)?.as_str().to_owned()))
+                })
+                .collect();
+            Some(RouteMatch { request, params })
+        })
+    }
+
+    /// Finds the route a request like `GET /posts/1?page=2` is sent to, a `HEAD` request falls
+    /// back to the `GET` routes
+    pub fn recognize(&self, method: &RequestMethod, path: &str) -> Option<RouteMatch<'_>> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            path => path,
+        };
+        self.find(method, path).or_else(|| match method {
+            RequestMethod::Head => self.find(&RequestMethod::Get, path),
+            _ => None,
+        })
+    }
+}
+
+// `{:id=>/\d+/}` or `{id: /\d+/}` in the last column of `rails routes`
+fn parse_table_constraints(input: &str) -> Vec<(String, String)> {
+    let mut constraints = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find('/') {
+        let key = rest[..start].trim_end_matches(['=', '>', ':', ' ']);
+        let key = &key[key
+            .rfind(|x: char| !(x.is_alphanumeric() || x == '_'))
+            .map_or(0, |x| x + 1)..];
+        let value = &rest[start + 1..];
+        let mut escaped = false;
+        let end = value.char_indices().find_map(|(index, c)| {
+            match c {
+                '/' if !escaped => return Some(index),
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+            None
+        });
+        let end = match end {
+            Some(end) => end,
+            None => break,
+        };
+        if !key.is_empty() {
+            constraints.push((key.to_owned(), value[..end].to_owned()));
+        }
+        rest = &value[end + 1..];
+    }
+    constraints
 }
 
 pub fn parse_routes(input: &str) -> Result<Vec<Request>, String> {
@@ -108,7 +986,6 @@ pub fn parse_routes(input: &str) -> Result<Vec<Request>, String> {
         let lines: Vec<Vec<String>> = input
             .lines()
             .skip(1)
-            .into_iter()
             .map(|f| {
                 f.split_whitespace()
                     .map(|e| e.to_string())
@@ -117,62 +994,51 @@ pub fn parse_routes(input: &str) -> Result<Vec<Request>, String> {
             })
             .collect();
 
-        // this ugly mess is grabbing the valid feilds but ignoring the last one if an extra resource thing is added on to the end as I don't know what it does
-        for line in &lines {
-            if line.len() == 5 {
-            } else if line.len() == 4 {
-                if let Ok(temp2) = RequestMethod::from_str(&line[0]) {
-                    let temp = line[2].split('#').collect::<Vec<&str>>();
-                    if temp.len() != 2 {
-                        return Err(format!(
-                            "could not find action on the contorller {}",
-                            line[2]
-                        ));
-                    }
-
-                    routes.push(Request {
-                        method: temp2,
-                        prefix: "".to_string(),
-                        uri: line[1].replace("(.:format)", ""),
-                        controller: temp[0].to_string() + "_controller",
-                        action: temp[1].to_string(),
-                    })
-                } else {
-                    let temp = line[3].split('#').collect::<Vec<&str>>();
-                    if temp.len() != 2 {
-                        return Err(format!(
-                            "could not find action on the contorller {}",
-                            line[3]
-                        ));
-                    }
-
-                    routes.push(Request {
-                        method: RequestMethod::from_str(&line[1])?,
-                        prefix: line[0].clone(),
-                        uri: line[2].replace("(.:format)", ""),
-                        controller: temp[0].to_string() + "_controller",
-                        action: temp[1].to_string(),
-                    })
+        for line in lines.iter().filter(|x| !x.is_empty()) {
+            // the columns before the uri are the optional name and the verb, `via: :all`
+            // routes have no verb
+            let uri_index = match line.iter().position(|x| x.starts_with('/')) {
+                Some(index) if index + 1 < line.len() => index,
+                _ => {
+                    println!("WARNING: unrecognized route line {}", line.join(" "));
+                    continue;
                 }
-            } else if line.len() == 3 {
-                let temp = line[2].split('#').collect::<Vec<&str>>();
-                if temp.len() != 2 {
-                    return Err(format!(
-                        "could not find action on the contorller {}",
-                        line[2]
-                    ));
+            };
+            let (prefix, methods) = match &line[..uri_index] {
+                [prefix, methods] => (prefix.as_str(), parse_methods(methods)?),
+                [methods] => match parse_methods(methods) {
+                    Ok(methods) => ("", methods),
+                    Err(_) => (methods.as_str(), Vec::new()),
+                },
+                [] => ("", Vec::new()),
+                _ => {
+                    println!("WARNING: unrecognized route line {}", line.join(" "));
+                    continue;
                 }
-
-                routes.push(Request {
-                    method: RequestMethod::from_str(&line[0])?,
-                    prefix: "".to_string(),
-                    uri: line[1].replace("(.:format)", ""),
-                    controller: temp[0].to_string() + "_controller",
-                    action: temp[1].to_string(),
-                })
-            } else {
-                println!("panic {:?}", line);
+            };
+            let target = &line[uri_index + 1];
+            let temp = target.split('#').collect::<Vec<&str>>();
+            if temp.len() != 2 {
+                return Err(format!(
+                    "could not find action on the contorller {}",
+                    target
+                ));
             }
+
+            let uri = line[uri_index].replace("(.:format)", "");
+            let mut segments = parse_segments(&uri);
+            apply_constraints(
+                &mut segments,
+                &parse_table_constraints(&line[uri_index + 2..].join(" ")),
+            );
+            routes.push(Request {
+                methods,
+                prefix: prefix.to_string(),
+                uri,
+                segments,
+                controller: temp[0].to_string() + "_controller",
+                action: temp[1].to_string(),
+            })
         }
 
         Ok(routes)
@@ -182,8 +1048,10 @@ pub fn parse_routes(input: &str) -> Result<Vec<Request>, String> {
 #[cfg(test)]
 mod routes_parsing {
     use super::parse_routes;
+    use super::parse_segments;
     use super::Request;
     use super::RequestMethod;
+    use super::RouteTable;
 
     #[test]
     fn parse() {
@@ -198,14 +1066,15 @@ mod routes_parsing {
        new_dog_style GET     /dogs/:dog_id/styles/new(.:format)                                                 dogs/styles#new
         ";
 
-        assert_eq!(parse_routes(input).is_ok(), true, "successfully parse");
+        assert!(parse_routes(input).is_ok(), "successfully parse");
         assert_eq!(parse_routes(input).unwrap().len(), 8);
         assert_eq!(
             parse_routes(input).unwrap()[0],
             Request {
-                method: RequestMethod::Post,
+                methods: vec![RequestMethod::Post],
                 prefix: "email_processor".to_string(),
                 uri: "/email_processor".to_string(),
+                segments: parse_segments("/email_processor"),
                 controller: "griddler/emails_controller".to_string(),
                 action: "create".to_string(),
             }
@@ -214,12 +1083,837 @@ mod routes_parsing {
         assert_eq!(
             parse_routes(input).unwrap()[2],
             Request {
-                method: RequestMethod::Patch,
+                methods: vec![RequestMethod::Patch],
                 prefix: "".to_string(),
                 uri: "/dog/form".to_string(),
+                segments: parse_segments("/dog/form"),
                 controller: "dog_forms_controller".to_string(),
                 action: "update".to_string(),
             }
         );
     }
+
+    #[test]
+    fn multiple_methods() {
+        let input = "Prefix Verb    URI Pattern                Controller#Action
+              status HEAD     /status(.:format)          status#show
+              search GET|POST /search(.:format)          search#index
+                ping          /ping(.:format)            health#ping
+                              /pong(.:format)            health#pong
+        ";
+
+        let routes = parse_routes(input).unwrap();
+        assert_eq!(
+            routes.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+            ["Head /status", "Get|Post /search", "Any /ping", "Any /pong"]
+        );
+        assert_eq!(routes[2].prefix, "ping");
+        assert!(parse_routes(
+            "Prefix Verb URI Pattern Controller#Action\n x LINK /x(.:format) x#y"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn segments() {
+        let input = "Prefix Verb    URI Pattern                          Controller#Action
+                file GET     /files/*path(.:format)               files#show {:path=>/[a-z\\/]+/}
+               posts GET     /posts(/:page)(.:format)             posts#index
+                user GET     /u/:id(.:format)                     users#show {id: /\\d+/}
+             archive GET     /archive(/:year(/:month))(.:format)  archive#show
+        ";
+        let routes = parse_routes(input).unwrap();
+
+        assert_eq!(routes[1].uri, "/posts(/:page)");
+        assert_eq!(
+            routes
+                .iter()
+                .map(|x| x
+                    .get_path_params()
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","))
+                .collect::<Vec<_>>(),
+            ["*path /[a-z\\/]+/", "page?", "id /\\d+/", "year?,month?"]
+        );
+        assert_eq!(
+            routes[3]
+                .segments
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<String>(),
+            routes[3].uri
+        );
+        assert_eq!(
+            routes[3].expand_paths(),
+            ["/archive", "/archive/:year", "/archive/:year/:month"]
+        );
+        assert_eq!(routes[0].expand_paths(), ["/files/*path"]);
+    }
+
+    #[test]
+    fn recognize() {
+        let input = "Prefix Verb    URI Pattern                    Controller#Action
+            dog_form GET     /dog/form(.:format)            dog_forms#show
+                     PATCH   /dog/form(.:format)            dog_forms#update
+               posts GET     /posts(/:page)(.:format)       posts#index
+                user GET     /u/:id(.:format)               users#show {id: /\\d+/}
+        user_by_name GET     /u/:name(.:format)             users#by_name
+                file GET     /files/*path(.:format)         files#show
+                     GET     /pages/*                       pages#catch_all
+                ping         /ping(.:format)                health#ping
+        ";
+        let table = RouteTable::new(parse_routes(input).unwrap());
+        let recognize = |method: RequestMethod, path: &str| {
+            table.recognize(&method, path).map(|x| {
+                let mut params: Vec<_> = x
+                    .params
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect();
+                params.sort();
+                format!("{} {}", x.request.action, params.join(","))
+            })
+        };
+
+        assert_eq!(table.len(), 8);
+        assert_eq!(
+            recognize(RequestMethod::Patch, "/dog/form").as_deref(),
+            Some("update ")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/dog/form.json").as_deref(),
+            Some("show format=json")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/posts/2?sort=new").as_deref(),
+            Some("index page=2")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/posts/").as_deref(),
+            Some("index ")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/u/12").as_deref(),
+            Some("show id=12")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/u/bob").as_deref(),
+            Some("by_name name=bob")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/files/a/b.txt").as_deref(),
+            Some("show format=txt,path=a/b")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Head, "/u/1").as_deref(),
+            Some("show id=1")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Delete, "/ping").as_deref(),
+            Some("ping ")
+        );
+        assert_eq!(
+            recognize(RequestMethod::Get, "/pages/a/b").as_deref(),
+            Some("catch_all ")
+        );
+        assert_eq!(recognize(RequestMethod::Post, "/posts"), None);
+    }
+}
+
+#[cfg(test)]
+mod request_tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::parse_segments;
+    use super::Request;
+    use super::RequestMethod;
+    use crate::app_data_from_source;
+    use crate::models::models_from_source;
+    use crate::types::Status;
+    use crate::types::{View, ViewType};
+    use crate::views::view_from_source;
+
+    fn request(action: &str) -> Request {
+        Request {
+            methods: vec![RequestMethod::Get],
+            prefix: "".to_string(),
+            uri: "/pages".to_string(),
+            segments: parse_segments("/pages"),
+            controller: "pages_controller".to_string(),
+            action: action.to_string(),
+        }
+    }
+
+    #[test]
+    fn private_action_is_flagged() {
+        let app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            def index
+                params[:id]
+            end
+
+            private
+
+            def user_details
+                params[:user_id]
+            end
+        end
+        "]);
+        assert!(request("index").get_params(&app_data).is_ok());
+        assert!(request("user_details")
+            .get_params(&app_data)
+            .unwrap_err()
+            .contains("Private"));
+        assert!(request("missing").get_params(&app_data).is_err());
+    }
+
+    #[test]
+    fn action_methods_are_public() {
+        let app_data = app_data_from_source(&[
+            "
+        class ApplicationController < ActionController::Base
+            def health
+            end
+        end
+        ",
+            "
+        class PagesController < ApplicationController
+            def index
+            end
+
+            def self.cache_key
+            end
+
+            def show
+            end
+
+            private
+
+            def user_details
+            end
+        end
+        ",
+        ]);
+        let actions = app_data.controllers["PagesController"].get_action_methods(&app_data);
+        let names: Vec<&str> = actions.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["index", "show", "health"]);
+    }
+
+    #[test]
+    fn ivar_flow() {
+        let mut app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            before_action :load_page
+
+            def show
+                @data = Post.find(params[:id])
+            end
+
+            private
+
+            def load_page
+                @page_index = params[:index]
+            end
+        end
+        "]);
+        let mut views = HashMap::new();
+        views.insert(
+            "show".to_owned(),
+            View {
+                controller: "pages".to_owned(),
+                method: "show.jbuilder".to_owned(),
+                format: None,
+                variant: None,
+                response: Vec::new(),
+                conditionals: Vec::new(),
+                sources: HashMap::new(),
+                helper_calls: Vec::new(),
+                view_type: ViewType::Jbuilder,
+                instance_varaibles: ["@data", "@options"]
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect(),
+            },
+        );
+        app_data.views.insert("pages".to_owned(), views);
+
+        let flow = request("show").get_ivar_flow(&app_data).unwrap();
+        assert_eq!(
+            flow.assigned["@data"][0].to_string(),
+            "Post.find(params[id])"
+        );
+        assert_eq!(flow.get_unassigned(), ["@options"]);
+        assert_eq!(flow.get_unrendered(), ["@page_index"]);
+    }
+
+    #[test]
+    fn template_resolution() {
+        let mut app_data = app_data_from_source(&[
+            "
+        class Admin::BaseController < ApplicationController
+        end
+        ",
+            "
+        class PagesController < Admin::BaseController
+            def index
+            end
+
+            def show
+                render 'shared/item'
+            end
+
+            def edit
+                render :index
+            end
+        end
+        ",
+        ]);
+        let view = |controller: &str, method: &str| View {
+            controller: controller.to_owned(),
+            method: method.to_owned(),
+            format: Some("json".to_owned()),
+            variant: None,
+            response: vec![controller.to_owned()],
+            conditionals: Vec::new(),
+            sources: HashMap::new(),
+            helper_calls: Vec::new(),
+            view_type: ViewType::Jbuilder,
+            instance_varaibles: HashSet::new(),
+        };
+        for (controller, action) in [
+            ("admin/base", "index"),
+            ("shared", "item"),
+            ("application", "edit"),
+        ] {
+            app_data
+                .views
+                .entry(controller.to_owned())
+                .or_default()
+                .insert(
+                    action.to_owned(),
+                    view(controller, &format!("{}.json.jbuilder", action)),
+                );
+        }
+
+        assert_eq!(
+            request("index").get_view_prefixes(&app_data),
+            ["pages", "admin/base", "application"]
+        );
+        let template = |action: &str| -> Vec<String> {
+            let request = request(action);
+            request
+                .get_responses(&app_data)
+                .unwrap()
+                .iter()
+                .filter_map(|x| request.get_template(&app_data, x))
+                .map(|x| x.controller.clone())
+                .collect()
+        };
+        assert_eq!(template("index"), ["admin/base"]);
+        assert_eq!(template("show"), ["shared"]);
+        assert_eq!(template("edit"), ["admin/base"]);
+        assert_eq!(request("edit").get_view(&app_data).unwrap(), "application");
+    }
+
+    #[test]
+    fn view_types() {
+        let mut app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            def show
+                @data = Post.find(params[:id])
+            end
+        end
+        "]);
+        (app_data.tables, app_data.models) = models_from_source(
+            r#"
+        create_table "posts" do |t|
+            t.string "title", null: false
+            t.bigint "editor_id"
+        end
+        create_table "users" do |t|
+            t.string "name", null: false
+        end
+        create_table "categories" do |t|
+            t.string "name"
+        end
+        create_table "covers" do |t|
+            t.string "url", null: false
+        end
+        "#,
+            &[
+                "
+        class Post < ApplicationRecord
+            belongs_to :editor, class_name: 'User', optional: true
+            has_many :categories
+            has_one :cover
+        end
+        ",
+                "
+        class Cover < ApplicationRecord
+        end
+        ",
+                "
+        class User < ApplicationRecord
+        end
+        ",
+                "
+        class Category < ApplicationRecord
+        end
+        ",
+            ],
+        );
+        let view = view_from_source(
+            "pages",
+            "show",
+            "
+        json.(@data, :id, :title)
+        json.editor do
+            json.name @data.editor&.name
+        end
+        json.categories @data.categories do |category|
+            json.(category, :name)
+        end
+        json.editors @data.categories, :id
+        json.summary @data.summary_text
+        json.owner @data.editor
+        json.cover_url @data.cover.url
+        ",
+        );
+
+        let types: Vec<String> = request("show")
+            .get_view_types(&app_data, &view)
+            .iter()
+            .map(|(key, x)| format!("{}:{}", key, x))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "id:bigint",
+                "title:string",
+                "editor.name:string?",
+                "categories.name:string?",
+                "editors.id:bigint",
+                "summary:unknown",
+                "owner:User?",
+                "cover_url:string?"
+            ]
+        );
+    }
+
+    #[test]
+    fn view_helpers() {
+        let mut app_data = app_data_from_source(&[
+            "
+        class ApplicationController < ActionController::API
+            helper_method :current_user
+
+            private
+
+            def current_user
+                @current_user ||= User.find(session[:user_id])
+            end
+        end
+        ",
+            "
+        class PagesController < ApplicationController
+            helper :format
+
+            def show
+                @post = Post.find(params[:id])
+            end
+        end
+        ",
+            "
+        module FormatHelper
+            def status_label(post)
+                post.published? ? 'Published' : nil
+            end
+        end
+        ",
+            "
+        module ApplicationHelper
+            def word_count(post)
+                post.body.split.size
+            end
+        end
+        ",
+            "
+        module AdminHelper
+            def admin_only
+            end
+        end
+        ",
+        ]);
+        (app_data.tables, app_data.models) = models_from_source(
+            r#"
+        create_table "users" do |t|
+            t.string "name", null: false
+        end
+        "#,
+            &["
+        class User < ApplicationRecord
+        end
+        "],
+        );
+        let view = view_from_source(
+            "pages",
+            "show",
+            "
+        json.status status_label(@post)
+        json.words word_count(@post)
+        json.user_name current_user.name
+        json.url post_url(@post)
+        json.title t('posts.title')
+        json.admin admin_only
+        ",
+        );
+        let request = request("show");
+
+        assert_eq!(
+            request.get_view_helpers(&app_data),
+            ["FormatHelper", "ApplicationHelper"]
+        );
+        let types: Vec<String> = request
+            .get_view_types(&app_data, &view)
+            .iter()
+            .map(|(key, x)| format!("{}:{}", key, x))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "status:string?",
+                "words:unknown",
+                "user_name:string",
+                "url:unknown",
+                "title:unknown",
+                "admin:unknown"
+            ]
+        );
+        assert_eq!(
+            request.get_missing_view_helpers(&app_data, &view),
+            ["admin_only"]
+        );
+    }
+
+    #[test]
+    fn responses() {
+        let app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            before_action :authenticate
+
+            def index
+            end
+
+            def create
+                if save
+                    render json: @page, status: :created
+                else
+                    render json: @page.errors, status: :unprocessable_entity
+                end
+            end
+
+            private
+
+            def authenticate
+                head :unauthorized unless current_user
+            end
+        end
+        "]);
+        let responses = |action: &str| -> Vec<String> {
+            request(action)
+                .get_responses(&app_data)
+                .unwrap()
+                .iter()
+                .map(|x| x.to_string())
+                .collect()
+        };
+        assert_eq!(responses("index"), ["200 - template", "401 - nothing"]);
+        assert_eq!(
+            responses("create"),
+            [
+                "201 application/json json @page",
+                "401 - nothing",
+                "422 application/json json @page.errors()"
+            ]
+        );
+    }
+
+    #[test]
+    fn respond_to_formats() {
+        let mut app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            def show
+                @data = Post.find(params[:id])
+                respond_to do |format|
+                    format.html
+                    format.json
+                    format.csv { render plain: @data.to_csv }
+                end
+            end
+        end
+        "]);
+        let mut views = HashMap::new();
+        views.insert(
+            "show".to_owned(),
+            View {
+                controller: "pages".to_owned(),
+                method: "show.jbuilder".to_owned(),
+                format: None,
+                variant: None,
+                response: Vec::new(),
+                conditionals: Vec::new(),
+                sources: HashMap::new(),
+                helper_calls: Vec::new(),
+                view_type: ViewType::Jbuilder,
+                instance_varaibles: HashSet::new(),
+            },
+        );
+        app_data.views.insert("pages".to_owned(), views);
+
+        let request = request("show");
+        let responses = request.get_responses(&app_data).unwrap();
+        assert_eq!(
+            responses.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+            [
+                "200 application/json template [json]",
+                "200 text/html template [html]",
+                "200 text/plain plain @data.to_csv() [csv]",
+                "404 - nothing unrescued ActiveRecord::RecordNotFound"
+            ]
+        );
+        assert!(request.get_template(&app_data, &responses[0]).is_some());
+        assert!(request.get_template(&app_data, &responses[1]).is_none());
+        assert_eq!(
+            request.get_content_types(&app_data).unwrap(),
+            ["application/json", "text/html", "text/plain"]
+        );
+    }
+
+    #[test]
+    fn rescue_from_responses() {
+        let app_data = app_data_from_source(&[
+            "
+            module ErrorHandling
+                extend ActiveSupport::Concern
+
+                included do
+                    rescue_from ActiveRecord::RecordNotFound do |e|
+                        render json: { error: e.message }, status: :not_found
+                    end
+                end
+            end
+            ",
+            "
+            class PagesController < ApplicationController
+                include ErrorHandling
+                rescue_from Pundit::NotAuthorizedError, with: :forbidden
+
+                def show
+                    params[:id]
+                end
+
+                private
+
+                def forbidden
+                    head :forbidden
+                end
+            end
+            ",
+        ]);
+        let request = request("show");
+        assert_eq!(request.get_params(&app_data).unwrap().len(), 1);
+        assert_eq!(
+            request
+                .get_responses(&app_data)
+                .unwrap()
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "200 - template",
+                "403 - nothing rescues Pundit::NotAuthorizedError",
+                "404 application/json json {error=>unknown} rescues ActiveRecord::RecordNotFound"
+            ]
+        );
+    }
+
+    #[test]
+    fn raised_exceptions() {
+        let app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            before_action :load_page
+            rescue_from ActiveRecord::ActiveRecordError, with: :unprocessable
+
+            def update
+                authorize @page
+                @page.update!(params.require(:page).permit(:title))
+            end
+
+            private
+
+            def load_page
+                @page = Page.find(params[:id])
+            end
+
+            def unprocessable
+                head :unprocessable_entity
+            end
+        end
+        "]);
+        let request = request("update");
+        assert_eq!(
+            request.get_raised_exceptions(&app_data).unwrap(),
+            [
+                "ActionController::ParameterMissing",
+                "ActiveRecord::RecordInvalid",
+                "ActiveRecord::RecordNotFound",
+                "Pundit::NotAuthorizedError"
+            ]
+        );
+        assert_eq!(
+            request
+                .get_responses(&app_data)
+                .unwrap()
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "200 - template",
+                "400 - nothing unrescued ActionController::ParameterMissing",
+                "403 - nothing unrescued Pundit::NotAuthorizedError",
+                "422 - nothing rescues ActiveRecord::ActiveRecordError"
+            ]
+        );
+    }
+
+    #[test]
+    fn serializer_responses() {
+        let mut app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            def show
+                @page = Blog::Page.find(params[:id])
+                render json: @page
+            end
+
+            def index
+                render json: @pages, each_serializer: PageSummarySerializer
+            end
+        end
+        "]);
+        app_data.serializers = crate::serializers::serializers_from_source(&[
+            "
+            module Blog
+                class PageSerializer < ActiveModel::Serializer
+                    attributes :id, :title
+                end
+            end
+            ",
+            "
+            class PageSummarySerializer < ActiveModel::Serializer
+                attributes :id
+            end
+            ",
+        ]);
+
+        let keys = |action: &str| {
+            let request = request(action);
+            let responses = request.get_responses(&app_data).unwrap();
+            let response = responses.iter().find(|x| x.status == Status::Code(200));
+            request.get_response_keys(&app_data, response.unwrap())
+        };
+        assert_eq!(keys("show").unwrap(), ["id", "title"]);
+        assert_eq!(keys("index").unwrap(), ["id"]);
+    }
+
+    #[test]
+    fn serializer_calls() {
+        let mut app_data = app_data_from_source(&["
+        class PagesController < ApplicationController
+            def show
+                @page = Page.find(params[:id])
+                render json: PageSerializer.new(@page, include: [:author]).serializable_hash
+            end
+
+            def index
+                render json: PageBlueprint.render(Page.all, view: :extended)
+            end
+        end
+        "]);
+        app_data.serializers = crate::serializers::serializers_from_source(&[
+            "
+            class PageSerializer
+                include JSONAPI::Serializer
+                belongs_to :author
+            end
+            ",
+            "
+            class AuthorSerializer
+                include JSONAPI::Serializer
+                attributes :name
+            end
+            ",
+            "
+            class PageBlueprint < Blueprinter::Base
+                identifier :id
+                view :extended do
+                    field :title
+                end
+            end
+            ",
+        ]);
+
+        let keys = |action: &str| {
+            let request = request(action);
+            let responses = request.get_responses(&app_data).unwrap();
+            let response = responses.iter().find(|x| x.status == Status::Code(200));
+            request.get_response_keys(&app_data, response.unwrap())
+        };
+        assert_eq!(
+            keys("show").unwrap(),
+            [
+                "data.id",
+                "data.type",
+                "data.relationships.author.data.id",
+                "data.relationships.author.data.type",
+                "included.id",
+                "included.type",
+                "included.attributes.name"
+            ]
+        );
+        assert_eq!(keys("index").unwrap(), ["id", "title"]);
+    }
+
+    #[test]
+    fn helper_renders_its_args() {
+        let app_data = app_data_from_source(&[
+            "
+            module HttpResponses
+                extend ActiveSupport::Concern
+
+                def json_ok(obj, response)
+                    render :status => response, :json => obj
+                end
+            end
+            ",
+            "
+            class PagesController < ApplicationController
+                include HttpResponses
+
+                def create
+                    json_ok({ id: 1, tags: @tags.map { |t| { name: t.name } } }, :created)
+                end
+            end
+            ",
+        ]);
+        let request = request("create");
+        let responses = request.get_responses(&app_data).unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].status, Status::Code(201));
+        assert_eq!(
+            request.get_response_keys(&app_data, &responses[0]).unwrap(),
+            ["id", "tags.name"]
+        );
+    }
 }
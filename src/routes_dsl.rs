@@ -0,0 +1,1086 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use convert_case::{Case, Casing};
+use lib_ruby_parser::{nodes::Send, traverse::Visitor, Node, Parser};
+use walkdir::WalkDir;
+
+use crate::{
+    responses,
+    routes::{apply_constraints, parse_segments, Mount, Request, RequestMethod},
+    utils,
+};
+
+const RESOURCES_ACTIONS: [&str; 7] = [
+    "index", "create", "new", "edit", "show", "update", "destroy",
+];
+
+// regex constraints like `id: /\d+/` are supported as well
+const RESOURCES_OPTIONS: [&str; 9] = [
+    "only",
+    "except",
+    "as",
+    "path",
+    "module",
+    "controller",
+    "param",
+    "concerns",
+    "constraints",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScopeLevel {
+    Plain,
+    Nested,
+    Member,
+    Collection,
+    New,
+}
+
+// the resource a `resources`/`resource` block is evaluated against
+#[derive(Clone)]
+struct Resource {
+    singular: bool,
+    controller: String,
+    collection_path: String,
+    collection_name: String,
+    member_path: String,
+    member_name: String,
+}
+
+// the equivalent of the route mapper's `@scope`, `name` is the `as` prefix of every route name
+#[derive(Clone)]
+struct Scope {
+    path: String,
+    module: String,
+    name: String,
+    controller: Option<String>,
+    level: ScopeLevel,
+    resource: Option<Resource>,
+    // path param name to its regex
+    constraints: Vec<(String, String)>,
+}
+
+impl Scope {
+    fn root() -> Scope {
+        Scope {
+            path: String::new(),
+            module: String::new(),
+            name: String::new(),
+            controller: None,
+            level: ScopeLevel::Plain,
+            resource: None,
+            constraints: Vec::new(),
+        }
+    }
+
+    fn with_constraints(&self, constraints: Vec<(String, String)>) -> Scope {
+        let mut scope = self.clone();
+        scope.constraints.extend(constraints);
+        scope
+    }
+
+    fn with_level(&self, level: ScopeLevel) -> Option<Scope> {
+        let resource = self.resource.as_ref()?;
+        let (path, name) = match level {
+            ScopeLevel::Member => (resource.member_path.clone(), resource.member_name.clone()),
+            ScopeLevel::Collection => (
+                resource.collection_path.clone(),
+                resource.collection_name.clone(),
+            ),
+            ScopeLevel::New => (
+                join_path(&resource.collection_path, "new"),
+                join_name(&["new", &resource.member_name]),
+            ),
+            _ => (self.path.clone(), self.name.clone()),
+        };
+        Some(Scope {
+            path,
+            name,
+            level,
+            ..self.clone()
+        })
+    }
+
+    // `get :preview` in a `resources` block is nested, in a `resource` block it is a member route
+    fn for_verb(&self, on: Option<&str>) -> Option<Scope> {
+        match on {
+            Some("member") => self.with_level(ScopeLevel::Member),
+            Some("collection") => self.with_level(ScopeLevel::Collection),
+            Some("new") => self.with_level(ScopeLevel::New),
+            Some(_) => None,
+            None => match &self.resource {
+                Some(resource) if self.level == ScopeLevel::Nested && resource.singular => {
+                    self.with_level(ScopeLevel::Member)
+                }
+                _ => Some(self.clone()),
+            },
+        }
+    }
+
+    fn route_name(&self, action: &str) -> String {
+        match self.level {
+            ScopeLevel::Member | ScopeLevel::Collection | ScopeLevel::New => {
+                join_name(&[action, &self.name])
+            }
+            ScopeLevel::Plain | ScopeLevel::Nested => join_name(&[&self.name, action]),
+        }
+    }
+}
+
+fn join_name(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .filter(|x| !x.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn join_path(base: &str, segment: &str) -> String {
+    match segment.trim_matches('/') {
+        "" => base.to_owned(),
+        segment => format!("{}/{}", base, segment),
+    }
+}
+
+fn join_module(module: &str, name: &str) -> String {
+    match name.trim_matches('/') {
+        "" => module.to_owned(),
+        name => format!("{}{}/", module, name),
+    }
+}
+
+// `:a`, `[:a, :b]` and `:a, :b`
+fn symbol_list(args: &[Node]) -> Vec<String> {
+    args.iter()
+        .flat_map(|arg| match arg {
+            Node::Array(array) => array.elements.iter().map(utils::parse_node_str).collect(),
+            Node::Sym(_) | Node::Str(_) => vec![utils::parse_node_str(arg)],
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+// `/\d+/` is `\d+`
+fn regexp_source(node: &Node) -> Option<String> {
+    match node {
+        Node::Regexp(regexp) => Some(regexp.parts.iter().map(utils::parse_node_str).collect()),
+        _ => None,
+    }
+}
+
+// `constraints: { id: /\d+/ }` and the `id: /\d+/` shorthand, request based constraints like
+// `subdomain: 'api'` or lambdas don't apply to the path so they are skipped
+fn parse_constraints(options: &[(String, &Node)]) -> Vec<(String, String)> {
+    options
+        .iter()
+        .flat_map(|(key, value)| match value {
+            Node::Regexp(_) => regexp_source(value)
+                .map(|x| (key.clone(), x))
+                .into_iter()
+                .collect(),
+            Node::Hash(_) if key == "constraints" => {
+                parse_constraints(&responses::options(std::slice::from_ref(*value)))
+            }
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+fn statements(body: Option<&Node>) -> Vec<&Node> {
+    match body {
+        Some(Node::Begin(begin)) => begin.statements.iter().collect(),
+        Some(node) => vec![node],
+        None => Vec::new(),
+    }
+}
+
+// `Api::Admin` is `api/admin`
+fn namespace_path(name: &str) -> String {
+    name.split("::")
+        .map(|x| x.to_case(Case::Snake))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// `Sidekiq::Web` and `ActionCable.server`
+fn app_name(node: &Node) -> String {
+    match node {
+        Node::Send(send) if send.recv.is_some() && send.args.is_empty() => format!(
+            "{}.{}",
+            send.recv.as_deref().map(app_name).unwrap_or_default(),
+            send.method_name
+        ),
+        node => utils::parse_node_str(node),
+    }
+}
+
+// an engine found in engines/*, with the routes its `Api::Engine.routes.draw` block draws
+#[derive(Clone)]
+struct Engine {
+    routes_file: PathBuf,
+    namespace: Option<String>,
+}
+
+#[derive(Default)]
+struct IsolateNamespaceFinder {
+    namespace: Option<String>,
+}
+
+impl Visitor for IsolateNamespaceFinder {
+    fn on_send(&mut self, node: &Send) {
+        if node.recv.is_none() && node.method_name == "isolate_namespace" {
+            self.namespace = node.args.first().map(utils::parse_node_str);
+        }
+        self.maybe_visit(&node.recv);
+        self.visit_all(&node.args);
+    }
+}
+
+fn parse_file(path: &Path) -> Option<Node> {
+    Parser::new(&fs::read(path).ok()?, Default::default())
+        .do_parse()
+        .ast
+}
+
+// the `Api::Engine` in `Api::Engine.routes.draw do ... end`
+fn find_engine_name(ast: &Node) -> Option<String> {
+    statements(Some(ast)).iter().find_map(|node| match node {
+        Node::Block(block) => match &*block.call {
+            Node::Send(draw) if draw.method_name == "draw" => match draw.recv.as_deref() {
+                Some(Node::Send(routes)) if routes.method_name == "routes" => {
+                    routes.recv.as_deref().map(utils::parse_node_str)
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn find_engines(root: &Path) -> HashMap<String, Engine> {
+    let mut engines = HashMap::new();
+    let mut engines_path = root.to_path_buf();
+    engines_path.push("engines");
+    let dirs = match fs::read_dir(&engines_path) {
+        Ok(dirs) => dirs,
+        Err(_) => return engines,
+    };
+    for dir in dirs.filter_map(|x| x.ok()).map(|x| x.path()) {
+        let mut routes_file = dir.clone();
+        routes_file.push("config");
+        routes_file.push("routes.rb");
+        let name = match parse_file(&routes_file).as_ref().and_then(find_engine_name) {
+            Some(name) => name,
+            None => continue,
+        };
+        let mut finder = IsolateNamespaceFinder::default();
+        for entry in WalkDir::new(dir.join("lib"))
+            .into_iter()
+            .filter_entry(|e| -> bool { !utils::is_hidden(e) })
+            .filter_map(|e| e.ok())
+        {
+            let f = entry.path();
+            if f.is_file() && f.display().to_string().ends_with(".rb") {
+                if let Some(ast) = parse_file(f) {
+                    finder.visit(&ast);
+                }
+            }
+        }
+        engines.insert(
+            name,
+            Engine {
+                routes_file,
+                namespace: finder.namespace.as_deref().map(namespace_path),
+            },
+        );
+    }
+    engines
+}
+
+#[derive(Default)]
+struct RoutesParser {
+    // config/routes, where `draw :admin` finds admin.rb
+    routes_dir: Option<PathBuf>,
+    engines: HashMap<String, Engine>,
+    concerns: HashMap<String, Option<Node>>,
+    names: HashSet<String>,
+    routes: Vec<Request>,
+    mounts: Vec<Mount>,
+}
+
+impl RoutesParser {
+    fn parse_body(&mut self, body: Option<&Node>, scope: &Scope) {
+        for statement in statements(body) {
+            self.parse(statement, scope);
+        }
+    }
+
+    fn parse(&mut self, node: &Node, scope: &Scope) {
+        let (send, body) = match node {
+            Node::Send(send) => (send, None),
+            Node::Block(block) => match &*block.call {
+                Node::Send(send) => (send, block.body.as_deref()),
+                _ => return,
+            },
+            Node::Begin(_) => return self.parse_body(Some(node), scope),
+            _ => return,
+        };
+        // `Rails.application.routes.draw do ... end`
+        if send.recv.is_some() {
+            if send.method_name == "draw" {
+                self.parse_body(body, scope);
+            }
+            return;
+        }
+
+        match send.method_name.as_str() {
+            "get" | "post" | "put" | "patch" | "delete" | "options" | "head" => {
+                match send.method_name.to_uppercase().parse::<RequestMethod>() {
+                    Ok(method) => self.parse_verb(send, vec![method], scope),
+                    Err(error) => println!("WARNING: {}", error),
+                }
+            }
+            "match" => self.parse_match(send, scope),
+            "root" => self.parse_root(send, scope),
+            "resources" => self.parse_resources(send, body, scope, false),
+            "resource" => self.parse_resources(send, body, scope, true),
+            "namespace" => self.parse_namespace(send, body, scope),
+            "scope" => self.parse_scope(send, body, scope),
+            "controller" => {
+                let mut inner = scope.clone();
+                inner.controller = send
+                    .args
+                    .first()
+                    .map(|x| scope.module.clone() + &utils::parse_node_str(x));
+                self.parse_body(body, &inner);
+            }
+            "member" | "collection" | "new" | "nested" => {
+                let level = match send.method_name.as_str() {
+                    "member" => ScopeLevel::Member,
+                    "collection" => ScopeLevel::Collection,
+                    "new" => ScopeLevel::New,
+                    _ => ScopeLevel::Nested,
+                };
+                match scope.with_level(level) {
+                    Some(inner) => self.parse_body(body, &inner),
+                    None => println!(
+                        "WARNING: {} used outside of a resource scope",
+                        send.method_name
+                    ),
+                }
+            }
+            "concern" => {
+                if let Some(name) = send.args.first() {
+                    self.concerns
+                        .insert(utils::parse_node_str(name), body.cloned());
+                }
+            }
+            "concerns" => self.expand_concerns(&symbol_list(&send.args), scope),
+            "constraints" => {
+                let inner =
+                    scope.with_constraints(parse_constraints(&responses::options(&send.args)));
+                self.parse_body(body, &inner);
+            }
+            "defaults" => self.parse_body(body, scope),
+            "draw" => self.parse_draw(send, scope),
+            "mount" => self.parse_mount(send, scope),
+            name => println!("WARNING: unsupported route method {}", name),
+        }
+    }
+
+    // `draw :admin` evaluates config/routes/admin.rb in the current scope
+    fn parse_draw(&mut self, send: &Send, scope: &Scope) {
+        for name in symbol_list(&send.args) {
+            let path = self
+                .routes_dir
+                .as_ref()
+                .map(|x| x.join(format!("{}.rb", name)));
+            match path.as_deref().and_then(parse_file) {
+                Some(ast) => self.parse(&ast, scope),
+                None => println!("WARNING: route file {}.rb not found", name),
+            }
+        }
+    }
+
+    // `mount Api::Engine => '/api', as: 'api'` and `mount Sidekiq::Web, at: '/sidekiq'`
+    fn parse_mount(&mut self, send: &Send, scope: &Scope) {
+        let options = responses::options(&send.args);
+        let option = |key: &str| {
+            options
+                .iter()
+                .find(|x| x.0 == key)
+                .map(|x| utils::parse_node_str(x.1))
+        };
+        let (app, path) = match send.args.first() {
+            Some(Node::Kwargs(kwargs)) => match kwargs.pairs.first() {
+                Some(Node::Pair(pair)) if !matches!(*pair.key, Node::Sym(_)) => (
+                    app_name(&pair.key),
+                    Some(utils::parse_node_str(&pair.value)),
+                ),
+                _ => return println!("WARNING: could not find the app being mounted"),
+            },
+            Some(node) => (app_name(node), option("at")),
+            None => return println!("WARNING: could not find the app being mounted"),
+        };
+        let path = match path {
+            Some(path) => join_path(&scope.path, &path),
+            None => return println!("WARNING: mount {} has no path", app),
+        };
+        let path = if path.is_empty() {
+            "/".to_owned()
+        } else {
+            path
+        };
+
+        match self.engines.get(&app).cloned() {
+            Some(engine) => {
+                let name = option("as").unwrap_or_else(|| {
+                    engine
+                        .namespace
+                        .clone()
+                        .unwrap_or_else(|| namespace_path(app.trim_end_matches("::Engine")))
+                        .replace('/', "_")
+                });
+                let mut parser = RoutesParser {
+                    routes_dir: engine.routes_file.parent().map(|x| x.join("routes")),
+                    engines: self.engines.clone(),
+                    ..Default::default()
+                };
+                let engine_scope = Scope {
+                    path: path.trim_end_matches('/').to_owned(),
+                    module: engine.namespace.map(|x| x + "/").unwrap_or_default(),
+                    ..Scope::root()
+                };
+                match parse_file(&engine.routes_file) {
+                    Some(ast) => parser.parse(&ast, &engine_scope),
+                    None => return println!("WARNING: could not parse the routes of {}", app),
+                }
+                // engine routes are reached through their mount e.g. `api.posts_path`
+                for mut route in parser.routes {
+                    if !route.prefix.is_empty() {
+                        route.prefix = format!("{}.{}", name, route.prefix);
+                    }
+                    self.routes.push(route);
+                }
+                self.mounts.extend(parser.mounts);
+            }
+            None => self.mounts.push(Mount {
+                name: option("as").unwrap_or_else(|| {
+                    app.split(['.', ':'])
+                        .filter(|x| !x.is_empty())
+                        .map(|x| x.to_case(Case::Snake))
+                        .collect::<Vec<_>>()
+                        .join("_")
+                }),
+                app,
+                path,
+            }),
+        }
+    }
+
+    fn expand_concerns(&mut self, names: &[String], scope: &Scope) {
+        for name in names {
+            match self.concerns.get(name).cloned() {
+                Some(body) => self.parse_body(body.as_ref(), scope),
+                None => println!("WARNING: route concern {} is not defined", name),
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        methods: Vec<RequestMethod>,
+        name: &str,
+        uri: &str,
+        controller: &str,
+        action: &str,
+        constraints: &[(String, String)],
+    ) {
+        // like rails only the first route with a name gets it
+        let prefix = if name.is_empty() || self.names.contains(name) {
+            String::new()
+        } else {
+            self.names.insert(name.to_owned());
+            name.to_owned()
+        };
+        let uri = if uri.is_empty() { "/" } else { uri };
+        let mut segments = parse_segments(uri);
+        apply_constraints(&mut segments, constraints);
+        self.routes.push(Request {
+            methods,
+            prefix,
+            uri: uri.to_owned(),
+            segments,
+            controller: controller.to_owned() + "_controller",
+            action: action.to_owned(),
+        });
+    }
+
+    // `get 'photos/search'`, `get :preview, on: :member`, `get 'about', to: 'pages#about'`
+    // and `get 'pages/index' => 'pages#index', as: :index`
+    fn parse_verb(&mut self, send: &Send, methods: Vec<RequestMethod>, scope: &Scope) {
+        let options = responses::options(&send.args);
+        let option = |name: &str| {
+            options
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|x| utils::parse_node_str(x.1))
+        };
+        let (path, mut to) = match send.args.first() {
+            Some(node @ (Node::Str(_) | Node::Sym(_))) => (utils::parse_node_str(node), None),
+            Some(Node::Kwargs(kwargs)) => match kwargs.pairs.first() {
+                Some(Node::Pair(pair)) if matches!(*pair.key, Node::Str(_)) => (
+                    utils::parse_node_str(&pair.key),
+                    Some(utils::parse_node_str(&pair.value)),
+                ),
+                _ => (String::new(), None),
+            },
+            _ => (String::new(), None),
+        };
+        if to.is_none() {
+            to = option("to");
+        }
+
+        let scope = match scope.for_verb(option("on").as_deref()) {
+            Some(scope) => scope,
+            None => {
+                return println!(
+                    "WARNING: {} {} has no resource scope",
+                    send.method_name, path
+                )
+            }
+        };
+        let uri = join_path(&scope.path, &path);
+        let mut controller = option("controller")
+            .map(|x| scope.module.clone() + &x)
+            .or_else(|| scope.controller.clone())
+            .or_else(|| scope.resource.as_ref().map(|x| x.controller.clone()));
+        let mut action = option("action");
+        match to.as_deref() {
+            Some(to) => match to.split_once('#') {
+                Some((to_controller, to_action)) => {
+                    if !to_controller.is_empty() {
+                        controller = Some(scope.module.clone() + to_controller);
+                    }
+                    action = Some(to_action.to_owned());
+                }
+                None if to == "unknown" => {
+                    return println!(
+                        "WARNING: {} {} does not route to an action",
+                        send.method_name, uri
+                    )
+                }
+                None => action = Some(to.to_owned()),
+            },
+            None if action.is_none() => {
+                let path = path.trim_matches('/');
+                match path.rsplit_once('/') {
+                    Some((path_controller, path_action)) if controller.is_none() => {
+                        controller = Some(scope.module.clone() + path_controller);
+                        action = Some(path_action.to_owned());
+                    }
+                    _ => action = Some(path.replace('-', "_")),
+                }
+            }
+            None => (),
+        }
+        let (controller, action) = match (controller, action) {
+            (Some(controller), Some(action)) if !action.is_empty() => (controller, action),
+            _ => {
+                return println!(
+                    "WARNING: could not find the controller and action for {} {}",
+                    send.method_name, uri
+                )
+            }
+        };
+
+        // only paths made of words get an automatic name
+        let name = match option("as") {
+            Some(name) => scope.route_name(&name),
+            None if !path.is_empty()
+                && path
+                    .chars()
+                    .all(|x| x.is_alphanumeric() || "_-/".contains(x)) =>
+            {
+                scope.route_name(&path.trim_matches('/').replace(['/', '-'], "_"))
+            }
+            None => String::new(),
+        };
+        let constraints = scope
+            .with_constraints(parse_constraints(&options))
+            .constraints;
+        self.push(methods, &name, &uri, &controller, &action, &constraints);
+    }
+
+    // `match 'photos', to: 'photos#show', via: [:get, :post]` and `via: :all`
+    fn parse_match(&mut self, send: &Send, scope: &Scope) {
+        let options = responses::options(&send.args);
+        let via = match options.iter().find(|(key, _)| key == "via") {
+            Some((_, via)) => symbol_list(std::slice::from_ref(via)),
+            None => return println!("WARNING: match without a via: option is not supported"),
+        };
+        if via.iter().any(|x| x == "all") {
+            return self.parse_verb(send, Vec::new(), scope);
+        }
+        match via
+            .iter()
+            .map(|x| x.to_uppercase().parse::<RequestMethod>())
+            .collect()
+        {
+            Ok(methods) => self.parse_verb(send, methods, scope),
+            Err(error) => println!("WARNING: {}", error),
+        }
+    }
+
+    // `root 'pages#home'` and `root to: 'pages#home'`
+    fn parse_root(&mut self, send: &Send, scope: &Scope) {
+        let to = match send.args.first() {
+            Some(node @ Node::Str(_)) => utils::parse_node_str(node),
+            _ => responses::options(&send.args)
+                .iter()
+                .find(|(key, _)| key == "to")
+                .map(|x| utils::parse_node_str(x.1))
+                .unwrap_or_default(),
+        };
+        match to.split_once('#') {
+            Some((controller, action)) => self.push(
+                vec![RequestMethod::Get],
+                &join_name(&[&scope.name, "root"]),
+                &scope.path,
+                &(scope.module.clone() + controller),
+                action,
+                &scope.constraints,
+            ),
+            None => println!("WARNING: could not find the controller and action for root"),
+        }
+    }
+
+    // `namespace :admin, path: 'adm', module: 'administration', as: 'adm'`
+    fn parse_namespace(&mut self, send: &Send, body: Option<&Node>, scope: &Scope) {
+        let name = send
+            .args
+            .first()
+            .map(utils::parse_node_str)
+            .unwrap_or_default();
+        let options = responses::options(&send.args);
+        let option = |key: &str| {
+            options
+                .iter()
+                .find(|x| x.0 == key)
+                .map(|x| utils::parse_node_str(x.1))
+                .unwrap_or_else(|| name.clone())
+        };
+        let inner = Scope {
+            path: join_path(&scope.path, &option("path")),
+            module: join_module(&scope.module, &option("module")),
+            name: join_name(&[&scope.name, &option("as")]),
+            constraints: scope.constraints.clone(),
+            ..Scope::root()
+        };
+        self.parse_body(body, &inner);
+    }
+
+    // `scope '/api'`, `scope path: 'api', module: 'api', as: 'api'` and `scope controller: :pages`
+    fn parse_scope(&mut self, send: &Send, body: Option<&Node>, scope: &Scope) {
+        let mut inner = scope.clone();
+        if let Some(node @ (Node::Str(_) | Node::Sym(_))) = send.args.first() {
+            inner.path = join_path(&inner.path, &utils::parse_node_str(node));
+        }
+        for (key, value) in responses::options(&send.args) {
+            let value = utils::parse_node_str(value);
+            match key.as_str() {
+                "path" => inner.path = join_path(&inner.path, &value),
+                "module" => inner.module = join_module(&inner.module, &value),
+                "as" => inner.name = join_name(&[&inner.name, &value]),
+                "controller" => inner.controller = Some(scope.module.clone() + &value),
+                _ => (),
+            }
+        }
+        let inner = inner.with_constraints(parse_constraints(&responses::options(&send.args)));
+        self.parse_body(body, &inner);
+    }
+
+    // `resources :posts, :comments, only: [:index], concerns: [:commentable] do ... end`
+    fn parse_resources(&mut self, send: &Send, body: Option<&Node>, scope: &Scope, singular: bool) {
+        let options = responses::options(&send.args);
+        let option = |key: &str| {
+            options
+                .iter()
+                .find(|x| x.0 == key)
+                .map(|x| utils::parse_node_str(x.1))
+        };
+        let list = |key: &str| {
+            options
+                .iter()
+                .find(|x| x.0 == key)
+                .map(|x| symbol_list(std::slice::from_ref(x.1)))
+        };
+        for (key, value) in &options {
+            if !RESOURCES_OPTIONS.contains(&key.as_str()) && !matches!(value, Node::Regexp(_)) {
+                println!("WARNING: unsupported route option {}", key);
+            }
+        }
+        let concerns = list("concerns").unwrap_or_default();
+        let param = option("param").unwrap_or_else(|| "id".to_owned());
+        let scope = &scope.with_constraints(parse_constraints(&options));
+
+        for name in symbol_list(&send.args) {
+            let as_name = option("as").unwrap_or_else(|| name.clone());
+            let path = join_path(&scope.path, &option("path").unwrap_or_else(|| name.clone()));
+            let module = join_module(&scope.module, &option("module").unwrap_or_default());
+            let controller = module.clone()
+                + &option("controller").unwrap_or_else(|| {
+                    if singular {
+                        utils::pluralize(&name)
+                    } else {
+                        name.clone()
+                    }
+                });
+            let (member_name, member_path, nested_path) = if singular {
+                (
+                    join_name(&[&scope.name, &as_name]),
+                    path.clone(),
+                    path.clone(),
+                )
+            } else {
+                let singular_name = utils::singularize(&name);
+                (
+                    join_name(&[&scope.name, &utils::singularize(&as_name)]),
+                    format!("{}/:{}", path, param),
+                    format!("{}/:{}_{}", path, singular_name, param),
+                )
+            };
+            let resource = Resource {
+                singular,
+                controller: controller.clone(),
+                collection_name: if singular {
+                    member_name.clone()
+                } else {
+                    join_name(&[&scope.name, &as_name])
+                },
+                collection_path: path,
+                member_path,
+                member_name: member_name.clone(),
+            };
+            let nested = Scope {
+                path: nested_path,
+                module,
+                name: member_name,
+                controller: None,
+                level: ScopeLevel::Nested,
+                resource: Some(resource.clone()),
+                constraints: scope.constraints.clone(),
+            };
+            self.parse_body(body, &nested);
+            self.expand_concerns(&concerns, &nested);
+
+            let actions: Vec<&str> = RESOURCES_ACTIONS
+                .iter()
+                .filter(|x| !singular || **x != "index")
+                .filter(|x| list("only").is_none_or(|only| only.iter().any(|y| y == *x)))
+                .filter(|x| list("except").is_none_or(|except| except.iter().all(|y| y != *x)))
+                .cloned()
+                .collect();
+            let mut add = |method: RequestMethod, name: &str, uri: &str, action: &str| {
+                if actions.contains(&action) {
+                    self.push(
+                        vec![method],
+                        name,
+                        uri,
+                        &controller,
+                        action,
+                        &scope.constraints,
+                    );
+                }
+            };
+            let new_name = join_name(&["new", &resource.member_name]);
+            let new_path = join_path(&resource.collection_path, "new");
+            let edit_name = join_name(&["edit", &resource.member_name]);
+            let edit_path = join_path(&resource.member_path, "edit");
+            add(
+                RequestMethod::Get,
+                &resource.collection_name,
+                &resource.collection_path,
+                "index",
+            );
+            add(
+                RequestMethod::Post,
+                &resource.collection_name,
+                &resource.collection_path,
+                "create",
+            );
+            add(RequestMethod::Get, &new_name, &new_path, "new");
+            add(RequestMethod::Get, &edit_name, &edit_path, "edit");
+            add(
+                RequestMethod::Get,
+                &resource.member_name,
+                &resource.member_path,
+                "show",
+            );
+            add(
+                RequestMethod::Patch,
+                &resource.member_name,
+                &resource.member_path,
+                "update",
+            );
+            add(
+                RequestMethod::Put,
+                &resource.member_name,
+                &resource.member_path,
+                "update",
+            );
+            add(
+                RequestMethod::Delete,
+                &resource.member_name,
+                &resource.member_path,
+                "destroy",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn routes_from_source(source: &str) -> Vec<Request> {
+    let mut parser = RoutesParser::default();
+    if let Some(ast) = Parser::new(source.as_bytes(), Default::default())
+        .do_parse()
+        .ast
+    {
+        parser.parse(&ast, &Scope::root());
+    }
+    parser.routes
+}
+
+/// Statically evaluates config/routes.rb of the project at `root`, following `draw` into
+/// config/routes/*.rb and `mount` into the engines in engines/*
+pub fn parse_routes_file(root: &Path) -> Result<(Vec<Request>, Vec<Mount>), String> {
+    let mut config_path = root.to_path_buf();
+    config_path.push("config");
+    let mut parser = RoutesParser {
+        routes_dir: Some(config_path.join("routes")),
+        engines: find_engines(root),
+        ..Default::default()
+    };
+    let ast = parse_file(&config_path.join("routes.rb"))
+        .ok_or_else(|| "could not parse config/routes.rb".to_owned())?;
+    parser.parse(&ast, &Scope::root());
+    Ok((parser.routes, parser.mounts))
+}
+
+#[cfg(test)]
+mod routes_dsl_tests {
+    use super::{parse_routes_file, routes_from_source};
+    use crate::routes::Request;
+    use pretty_assertions::assert_eq;
+    use std::path::Path;
+
+    fn routes(input: &str) -> Vec<String> {
+        format_routes(&routes_from_source(input))
+    }
+
+    fn format_routes(routes: &[Request]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|x| {
+                format!(
+                    "{} {} {}#{}",
+                    x.prefix,
+                    x,
+                    x.controller.trim_end_matches("_controller"),
+                    x.action
+                )
+                .trim()
+                .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resources() {
+        let input = "
+        Rails.application.routes.draw do
+            root 'pages#home'
+            get 'pages/index' => 'pages#index'
+            get 'about', to: 'pages#about', as: :about_us
+
+            resources :posts, only: [:index, :show] do
+                get :preview, on: :member
+                collection do
+                    get :search
+                end
+                resources :comments, except: [:edit, :update]
+            end
+            resource :profile, only: [:show, :update, :create, :new]
+            resources :tags, only: :index, module: :blog, shallow: true do
+                resources :votes, only: :index
+            end
+        end
+        ";
+
+        assert_eq!(
+            routes(input),
+            [
+                "root Get / pages#home",
+                "pages_index Get /pages/index pages#index",
+                "about_us Get /about pages#about",
+                "preview_post Get /posts/:id/preview posts#preview",
+                "search_posts Get /posts/search posts#search",
+                "post_comments Get /posts/:post_id/comments comments#index",
+                "Post /posts/:post_id/comments comments#create",
+                "new_post_comment Get /posts/:post_id/comments/new comments#new",
+                "post_comment Get /posts/:post_id/comments/:id comments#show",
+                "Delete /posts/:post_id/comments/:id comments#destroy",
+                "posts Get /posts posts#index",
+                "post Get /posts/:id posts#show",
+                "profile Post /profile profiles#create",
+                "new_profile Get /profile/new profiles#new",
+                "Get /profile profiles#show",
+                "Patch /profile profiles#update",
+                "Put /profile profiles#update",
+                "tag_votes Get /tags/:tag_id/votes blog/votes#index",
+                "tags Get /tags blog/tags#index",
+            ]
+        );
+    }
+
+    #[test]
+    fn namespaces_and_scopes() {
+        let input = "
+        Rails.application.routes.draw do
+            namespace :admin do
+                root to: 'dashboard#show'
+                resources :users, only: :index
+            end
+            scope '/api', module: :api, as: :api do
+                get 'status', to: 'health#show'
+            end
+            controller :pages do
+                get 'terms'
+            end
+            match 'search', to: 'search#index', via: [:get, :post]
+            match 'ping', to: 'health#ping', via: :all
+            head 'ping', to: 'health#head'
+        end
+        ";
+
+        assert_eq!(
+            routes(input),
+            [
+                "admin_root Get /admin admin/dashboard#show",
+                "admin_users Get /admin/users admin/users#index",
+                "api_status Get /api/status api/health#show",
+                "terms Get /terms pages#terms",
+                "search Get|Post /search search#index",
+                "ping Any /ping health#ping",
+                "Head /ping health#head",
+            ]
+        );
+    }
+
+    #[test]
+    fn concerns() {
+        let input = "
+        Rails.application.routes.draw do
+            concern :commentable do
+                resources :comments, only: [:index, :create]
+            end
+            concern :archivable do
+                post :archive, on: :member
+            end
+
+            resources :posts, only: :show, concerns: [:commentable, :archivable]
+            resources :photos, only: [] do
+                concerns :commentable
+            end
+            namespace :admin do
+                resources :articles, only: [], concerns: :commentable
+            end
+        end
+        ";
+
+        assert_eq!(
+            routes(input),
+            [
+                "post_comments Get /posts/:post_id/comments comments#index",
+                "Post /posts/:post_id/comments comments#create",
+                "archive_post Post /posts/:id/archive posts#archive",
+                "post Get /posts/:id posts#show",
+                "photo_comments Get /photos/:photo_id/comments comments#index",
+                "Post /photos/:photo_id/comments comments#create",
+                "admin_article_comments Get /admin/articles/:article_id/comments admin/comments#index",
+                "Post /admin/articles/:article_id/comments admin/comments#create",
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_and_mount() {
+        let (routes, mounts) =
+            parse_routes_file(Path::new("tests/resources/routes_test_case")).unwrap();
+
+        assert_eq!(
+            format_routes(&routes),
+            [
+                "admin_users Get /admin/users admin/users#index",
+                "status Get /status status#show",
+                "api.post_comments Get /api/posts/:post_id/comments api/comments#index",
+                "api.posts Get /api/posts api/posts#index",
+                "api.post Get /api/posts/:id api/posts#show",
+                "blog.articles Get /blog/articles articles#index",
+            ]
+        );
+        assert_eq!(
+            mounts
+                .iter()
+                .map(|x| format!("{} {}", x.name, x))
+                .collect::<Vec<_>>(),
+            [
+                "sidekiq_web Sidekiq::Web /sidekiq",
+                "cable ActionCable.server /cable"
+            ]
+        );
+    }
+
+    #[test]
+    fn constraints() {
+        let routes = routes_from_source(
+            r#"
+        Rails.application.routes.draw do
+            get 'u/:id', to: 'users#show', constraints: { id: /\d+/ }
+            get 'files/*path', to: 'files#show', path: /[a-z]+/
+            get 'posts(/:page)', to: 'posts#index'
+            constraints(year: /\d{4}/) do
+                get 'archive/:year', to: 'archive#show'
+            end
+            resources :photos, only: :show, constraints: { id: /[A-Z]\d+/ }
+        end
+        "#,
+        );
+
+        assert_eq!(
+            routes
+                .iter()
+                .map(|x| x
+                    .get_path_params()
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","))
+                .collect::<Vec<_>>(),
+            [
+                r"id /\d+/",
+                "*path /[a-z]+/",
+                "page?",
+                r"year /\d{4}/",
+                r"id /[A-Z]\d+/"
+            ]
+        );
+        assert_eq!(routes[2].expand_paths(), ["/posts", "/posts/:page"]);
+    }
+}
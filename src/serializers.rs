@@ -0,0 +1,614 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use convert_case::{Case, Casing};
+use lib_ruby_parser::{
+    nodes::{Class, Send},
+    Node, Parser,
+};
+use walkdir::WalkDir;
+
+use crate::{
+    types::{
+        AppData, Association, Serializer, SerializerAttribute, SerializerFields, SerializerKind,
+    },
+    utils,
+};
+
+impl Serializer {
+    /// Keys the serializer sends in the same format views use, associations are nested
+    /// e.g. `posts.id` and conditional keys are prefixed with `?`. `view` picks a Blueprinter
+    /// view and `include` the JSON:API relationships sent along in `included`.
+    pub fn get_response(
+        &self,
+        app_data: &AppData,
+        view: Option<&str>,
+        include: &[String],
+    ) -> Vec<String> {
+        let mut seen = vec![self.name.clone()];
+        match self.get_kind(app_data) {
+            SerializerKind::ActiveModel => self.get_keys(app_data, &mut seen),
+            SerializerKind::Blueprinter => {
+                self.get_blueprint_keys(app_data, view.unwrap_or("default"), &mut seen)
+            }
+            SerializerKind::JsonApi => self.get_json_api_keys(app_data, include),
+        }
+    }
+
+    // parents first, ending with this serializer
+    fn get_ancestors<'a>(&'a self, app_data: &'a AppData) -> Vec<&'a Serializer> {
+        let mut ancestors = vec![self];
+        while let Some(parent) = app_data.serializers.get(&ancestors[0].parent) {
+            if ancestors.iter().any(|x| x.name == parent.name) {
+                break;
+            }
+            ancestors.insert(0, parent);
+        }
+        ancestors
+    }
+
+    // `include JSONAPI::Serializer` in a parent applies to its subclasses too
+    fn get_kind(&self, app_data: &AppData) -> SerializerKind {
+        self.get_ancestors(app_data)
+            .into_iter()
+            .rev()
+            .map(|x| x.kind)
+            .find(|x| *x != SerializerKind::ActiveModel)
+            .unwrap_or_default()
+    }
+
+    fn get_association_serializer<'a>(
+        &self,
+        app_data: &'a AppData,
+        association: &Association,
+    ) -> Option<&'a Serializer> {
+        let suffix = match self.get_kind(app_data) {
+            SerializerKind::Blueprinter => "Blueprint",
+            _ => "Serializer",
+        };
+        let name = association.serializer.clone().unwrap_or_else(|| {
+            format!(
+                "{}{}",
+                utils::singularize(&association.name).to_case(Case::Pascal),
+                suffix
+            )
+        });
+        app_data.serializers.get(&name)
+    }
+
+    // `seen` stops serializers that include each other from recursing forever
+    fn get_keys(&self, app_data: &AppData, seen: &mut Vec<String>) -> Vec<String> {
+        let fields: Vec<&SerializerFields> = self
+            .get_ancestors(app_data)
+            .into_iter()
+            .map(|x| &x.fields)
+            .collect();
+        let mut results = Vec::new();
+        for attribute in fields.iter().flat_map(|x| &x.attributes) {
+            results.push(format!("{}{}", prefix(attribute.optional), attribute.name));
+        }
+
+        for association in fields.iter().flat_map(|x| &x.associations) {
+            let key = format!("{}{}", prefix(association.optional), association.name);
+            let nested = match self.get_association_serializer(app_data, association) {
+                Some(serializer) if !seen.contains(&serializer.name) => {
+                    seen.push(serializer.name.clone());
+                    let nested = serializer.get_keys(app_data, seen);
+                    seen.pop();
+                    nested
+                }
+                _ => Vec::new(),
+            };
+            results.append(&mut nest_keys(&key, association.many, nested));
+        }
+
+        results
+    }
+
+    // the default view is part of every other view along with any views they include
+    fn get_blueprint_fields<'a>(
+        &'a self,
+        app_data: &'a AppData,
+        view: &str,
+        results: &mut Vec<&'a SerializerFields>,
+        views: &mut Vec<String>,
+    ) {
+        if views.iter().any(|x| x == view) {
+            return;
+        }
+        views.push(view.to_owned());
+        for ancestor in self.get_ancestors(app_data) {
+            if view == "default" {
+                results.push(&ancestor.fields);
+            } else if let Some(fields) = ancestor.views.get(view) {
+                for included in &fields.include_views {
+                    self.get_blueprint_fields(app_data, included, results, views);
+                }
+                results.push(fields);
+            }
+        }
+    }
+
+    fn get_blueprint_keys(
+        &self,
+        app_data: &AppData,
+        view: &str,
+        seen: &mut Vec<String>,
+    ) -> Vec<String> {
+        let mut fields = Vec::new();
+        self.get_blueprint_fields(app_data, "default", &mut fields, &mut Vec::new());
+        self.get_blueprint_fields(app_data, view, &mut fields, &mut vec!["default".to_owned()]);
+
+        // blueprinter sorts by name with the identifier first
+        let mut keys: Vec<(&str, Vec<String>)> = Vec::new();
+        for attribute in fields.iter().flat_map(|x| &x.attributes) {
+            keys.push((
+                &attribute.name,
+                vec![format!("{}{}", prefix(attribute.optional), attribute.name)],
+            ));
+        }
+        for association in fields.iter().flat_map(|x| &x.associations) {
+            let key = format!("{}{}", prefix(association.optional), association.name);
+            let nested = match self.get_association_serializer(app_data, association) {
+                Some(serializer) if !seen.contains(&serializer.name) => {
+                    seen.push(serializer.name.clone());
+                    let view = association.view.as_deref().unwrap_or("default");
+                    let nested = serializer.get_blueprint_keys(app_data, view, seen);
+                    seen.pop();
+                    nested
+                }
+                _ => Vec::new(),
+            };
+            keys.push((&association.name, nest_keys(&key, association.many, nested)));
+        }
+        keys.sort_by_key(|x| x.0);
+        keys.dedup_by_key(|x| x.0);
+
+        let identifier = self
+            .get_ancestors(app_data)
+            .into_iter()
+            .rev()
+            .find_map(|x| x.identifier.clone());
+        identifier
+            .into_iter()
+            .chain(keys.into_iter().flat_map(|x| x.1))
+            .collect()
+    }
+
+    // a JSON:API document, relationships only send the id and type unless they are included
+    fn get_json_api_keys(&self, app_data: &AppData, include: &[String]) -> Vec<String> {
+        let mut results = vec!["data.id".to_owned(), "data.type".to_owned()];
+        let ancestors = self.get_ancestors(app_data);
+        for attribute in ancestors.iter().flat_map(|x| &x.fields.attributes) {
+            results.push(format!(
+                "data.attributes.{}{}",
+                prefix(attribute.optional),
+                attribute.name
+            ));
+        }
+
+        let mut included = Vec::new();
+        for association in ancestors.iter().flat_map(|x| &x.fields.associations) {
+            let key = format!(
+                "data.relationships.{}{}.data",
+                prefix(association.optional),
+                association.name
+            );
+            results.append(&mut nest_keys(
+                &key,
+                association.many,
+                vec!["id".to_owned(), "type".to_owned()],
+            ));
+            if include.contains(&association.name) {
+                if let Some(serializer) = self.get_association_serializer(app_data, association) {
+                    for attribute in serializer
+                        .get_ancestors(app_data)
+                        .iter()
+                        .flat_map(|x| &x.fields.attributes)
+                    {
+                        included.push(format!(
+                            "included.attributes.{}{}",
+                            prefix(attribute.optional),
+                            attribute.name
+                        ));
+                    }
+                }
+            }
+        }
+        if !include.is_empty() {
+            results.push("included.id".to_owned());
+            results.push("included.type".to_owned());
+            for key in included {
+                if !results.contains(&key) {
+                    results.push(key);
+                }
+            }
+        }
+
+        results
+    }
+}
+
+fn prefix(optional: bool) -> &'static str {
+    if optional {
+        "?"
+    } else {
+        ""
+    }
+}
+
+// nests keys under an association the way views do, `has_many` elements are wrapped in `[]`
+fn nest_keys(key: &str, many: bool, nested: Vec<String>) -> Vec<String> {
+    match (many, nested.is_empty()) {
+        (true, true) => vec![format!("{}.[]", key)],
+        (true, false) => nested.iter().map(|x| format!("{}.[{}]", key, x)).collect(),
+        (false, true) => vec![key.to_owned()],
+        (false, false) => nested.iter().map(|x| format!("{}.{}", key, x)).collect(),
+    }
+}
+
+// `key:`, `if:`, `serializer:` etc. from the end of the call
+fn options(send: &Send) -> HashMap<String, String> {
+    let pairs = match send.args.last() {
+        Some(Node::Kwargs(kwargs)) => &kwargs.pairs,
+        Some(Node::Hash(hash)) => &hash.pairs,
+        _ => return HashMap::new(),
+    };
+    pairs
+        .iter()
+        .filter_map(|pair| match pair {
+            Node::Pair(pair) => Some((
+                utils::parse_node_str(&pair.key),
+                utils::parse_node_str(&pair.value),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+// `view` is the Blueprinter view the call is inside of
+fn parse_serializer_send(send: &Send, serializer: &mut Serializer, view: Option<&str>) {
+    let options = options(send);
+    let optional = options.contains_key("if") || options.contains_key("unless");
+    let names: Vec<String> = send
+        .args
+        .iter()
+        .filter(|x| !matches!(x, Node::Kwargs(_) | Node::Hash(_)))
+        .map(utils::parse_node_str)
+        .collect();
+    // AMS renames with `key:` and Blueprinter with `name:`
+    let rename = options.get("key").or_else(|| options.get("name")).cloned();
+    match send.method_name.as_str() {
+        "include"
+            if names
+                .iter()
+                .any(|x| x == "JSONAPI::Serializer" || x == "FastJsonapi::ObjectSerializer") =>
+        {
+            serializer.kind = SerializerKind::JsonApi;
+            return;
+        }
+        "identifier" | "set_id" => {
+            serializer.identifier = names.into_iter().next();
+            return;
+        }
+        _ => {}
+    }
+
+    let fields = match view {
+        Some(view) => serializer.views.entry(view.to_owned()).or_default(),
+        None => &mut serializer.fields,
+    };
+    match send.method_name.as_str() {
+        "attributes" | "fields" => {
+            for name in names {
+                fields
+                    .attributes
+                    .push(SerializerAttribute { name, optional });
+            }
+        }
+        "attribute" | "field" => {
+            for name in names {
+                fields.attributes.push(SerializerAttribute {
+                    name: rename.clone().unwrap_or(name),
+                    optional,
+                });
+            }
+        }
+        "has_many" | "has_one" | "belongs_to" | "association" => {
+            for name in names {
+                fields.associations.push(Association {
+                    name: rename.clone().unwrap_or(name),
+                    many: send.method_name == "has_many",
+                    serializer: options
+                        .get("serializer")
+                        .or_else(|| options.get("each_serializer"))
+                        .or_else(|| options.get("blueprint"))
+                        .cloned(),
+                    view: options.get("view").cloned(),
+                    optional,
+                });
+            }
+        }
+        "include_view" | "include_views" => fields.include_views.extend(names),
+        _ => {}
+    }
+}
+
+fn parse_serializer_statements(
+    body: Option<Box<Node>>,
+    serializer: &mut Serializer,
+    view: Option<&str>,
+) {
+    let statements = match body.map(|x| *x) {
+        Some(Node::Begin(begin)) => begin.statements,
+        Some(node) => vec![node],
+        None => Vec::new(),
+    };
+    for statement in statements {
+        match statement {
+            Node::Send(send) => parse_serializer_send(&send, serializer, view),
+            Node::Block(block) => {
+                if let Node::Send(send) = *block.call {
+                    if send.method_name == "view" && view.is_none() {
+                        if let Some(name) = send.args.first().map(utils::parse_node_str) {
+                            parse_serializer_statements(block.body, serializer, Some(&name));
+                        }
+                    } else {
+                        // `attribute :name do ... end`, only the key matters
+                        parse_serializer_send(&send, serializer, view)
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_serializer_class(class: Class, module: &str) -> Serializer {
+    let parent = utils::parse_superclass(class.superclass);
+    let mut serializer = Serializer {
+        name: module.to_owned() + &utils::parse_name(*class.name),
+        kind: if parent == "Blueprinter::Base" || parent.ends_with("Blueprint") {
+            SerializerKind::Blueprinter
+        } else {
+            SerializerKind::ActiveModel
+        },
+        parent,
+        ..Default::default()
+    };
+    parse_serializer_statements(class.body, &mut serializer, None);
+
+    serializer
+}
+
+fn parse_serializers(node: Node, module: &str, serializers: &mut HashMap<String, Serializer>) {
+    match node {
+        Node::Class(class) => {
+            let serializer = parse_serializer_class(class, module);
+            serializers.insert(serializer.name.clone(), serializer);
+        }
+        Node::Module(module_node) => {
+            let module = format!("{}{}::", module, utils::parse_name(*module_node.name));
+            if let Some(body) = module_node.body {
+                parse_serializers(*body, &module, serializers);
+            }
+        }
+        Node::Begin(begin) => {
+            for statement in begin.statements {
+                parse_serializers(statement, module, serializers);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn parse_serializer_files(
+    path: &Path,
+    serializers: &mut HashMap<String, Serializer>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| -> bool { !utils::is_hidden(e) })
+        .filter_map(|e| e.ok())
+    {
+        let f = entry.path();
+        if f.is_file() && f.display().to_string().ends_with(".rb") {
+            let parser = Parser::new(&fs::read(f)?, Default::default()).do_parse();
+            if let Some(ast) = parser.ast {
+                parse_serializers(ast, "", serializers);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) fn serializers_from_source(sources: &[&str]) -> HashMap<String, Serializer> {
+    let mut serializers = HashMap::new();
+    for source in sources {
+        let ast = Parser::new(source.as_bytes(), Default::default())
+            .do_parse()
+            .ast
+            .unwrap();
+        parse_serializers(ast, "", &mut serializers);
+    }
+    serializers
+}
+
+#[cfg(test)]
+mod serializers_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::serializers_from_source;
+    use crate::types::AppData;
+
+    #[test]
+    fn attributes_and_associations() {
+        let mut app_data = AppData::new(Vec::new());
+        app_data.serializers = serializers_from_source(&[
+            "
+            class UserSerializer < ActiveModel::Serializer
+                attributes :id, :name
+                attribute :email, if: :admin?
+                attribute :full_name do
+                    \"#{object.first_name} #{object.last_name}\"
+                end
+
+                has_many :posts
+                belongs_to :team, serializer: Teams::SummarySerializer
+            end
+            ",
+            "
+            class PostSerializer < ActiveModel::Serializer
+                attributes :id, :title
+                belongs_to :user
+            end
+            ",
+            "
+            module Teams
+                class SummarySerializer < ActiveModel::Serializer
+                    attribute :name, key: :title
+                end
+            end
+            ",
+        ]);
+
+        assert_eq!(
+            app_data.serializers["UserSerializer"].get_response(&app_data, None, &[]),
+            [
+                "id",
+                "name",
+                "?email",
+                "full_name",
+                "posts.[id]",
+                "posts.[title]",
+                "posts.[user]",
+                "team.title"
+            ]
+        );
+    }
+
+    #[test]
+    fn inherited_attributes() {
+        let mut app_data = AppData::new(Vec::new());
+        app_data.serializers = serializers_from_source(&[
+            "
+            class BaseSerializer < ActiveModel::Serializer
+                attributes :id, :created_at
+            end
+            ",
+            "
+            class PageSerializer < BaseSerializer
+                attributes :title
+            end
+            ",
+        ]);
+
+        assert_eq!(
+            app_data.serializers["PageSerializer"].get_response(&app_data, None, &[]),
+            ["id", "created_at", "title"]
+        );
+    }
+
+    #[test]
+    fn blueprinter_views() {
+        let mut app_data = AppData::new(Vec::new());
+        app_data.serializers = serializers_from_source(&[
+            "
+            class UserBlueprint < Blueprinter::Base
+                identifier :uuid
+                fields :name, :email
+
+                view :normal do
+                    field :full_name do |user|
+                        user.name
+                    end
+                end
+
+                view :extended do
+                    include_view :normal
+                    association :projects, blueprint: ProjectBlueprint, view: :summary
+                end
+            end
+            ",
+            "
+            class ProjectBlueprint < Blueprinter::Base
+                identifier :id
+                field :description
+
+                view :summary do
+                    field :title
+                end
+            end
+            ",
+        ]);
+        let user = &app_data.serializers["UserBlueprint"];
+
+        assert_eq!(
+            user.get_response(&app_data, None, &[]),
+            ["uuid", "email", "name"]
+        );
+        assert_eq!(
+            user.get_response(&app_data, Some("extended"), &[]),
+            [
+                "uuid",
+                "email",
+                "full_name",
+                "name",
+                "projects.id",
+                "projects.description",
+                "projects.title"
+            ]
+        );
+    }
+
+    #[test]
+    fn json_api() {
+        let mut app_data = AppData::new(Vec::new());
+        app_data.serializers = serializers_from_source(&[
+            "
+            class UserSerializer
+                include JSONAPI::Serializer
+                set_type :user
+                attributes :name, :email
+                attribute :admin, if: Proc.new { |user| user.admin? }
+                has_many :posts
+                belongs_to :team
+            end
+            ",
+            "
+            class BaseSerializer
+                include JSONAPI::Serializer
+            end
+            ",
+            "
+            class PostSerializer < BaseSerializer
+                attributes :title
+            end
+            ",
+        ]);
+        let user = &app_data.serializers["UserSerializer"];
+
+        assert_eq!(
+            user.get_response(&app_data, None, &["posts".to_owned()]),
+            [
+                "data.id",
+                "data.type",
+                "data.attributes.name",
+                "data.attributes.email",
+                "data.attributes.?admin",
+                "data.relationships.posts.data.[id]",
+                "data.relationships.posts.data.[type]",
+                "data.relationships.team.data.id",
+                "data.relationships.team.data.type",
+                "included.id",
+                "included.type",
+                "included.attributes.title"
+            ]
+        );
+        assert_eq!(
+            app_data.serializers["PostSerializer"].get_response(&app_data, None, &[]),
+            ["data.id", "data.type", "data.attributes.title"]
+        );
+    }
+}
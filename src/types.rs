@@ -1,25 +1,167 @@
-use crate::routes::Request;
+use crate::routes::{Mount, Request, RouteTable};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Visibility {
+    #[default]
+    Public,
+    Private,
+    Protected,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct MethodDetails {
     pub name: String,
     pub args: Vec<String>,
+    pub visibility: Visibility,
+    // `def self.foo`
+    pub class_method: bool,
     pub params: HashSet<String>,
     pub headers: Vec<(String, String)>, // TODO: need to implement this one
 
     pub instance_varaibles: HashSet<String>, // implemented
+    // every value assigned to an instance varaible e.g. `@data = Post.find(params[:id])`
+    pub ivar_assignments: Vec<(String, Value)>,
     pub local_varaibles: HashMap<String, usize>, // implemented
+    // byte offset of where each local varaible or arg is first defined
+    pub local_varaible_offsets: HashMap<String, usize>,
+
+    pub method_calls: Vec<MethodCall>, // is nearly done
+    // every `render`, `head` and `redirect_to` in the method
+    pub renders: Vec<Response>,
+    // what the method's last expression, or a `return`, gives back
+    pub returns: Vec<ReturnValue>,
+    pub calls_super: bool,
+    // file the method was parsed from, empty when it didn't come from a file
+    pub file: String,
+}
+
+// what a method call was sent to e.g. `User.where(...)` is sent to `Const("User")`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Receiver {
+    ImplicitSelf,
+    ExplicitSelf,
+    Const(String),
+    Ivar(String),
+    Lvar(String),
+    Call(Box<MethodCall>),
+    // literals, indexes, parenthesised expressions etc. as best as `parse_node_str` can
+    Other(String),
+}
+
+// what gets assigned to an instance varaible
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    // a const, varaible or method call, anything methods can be sent to
+    Receiver(Receiver),
+    // literals, indexes etc. as best as `parse_node_str` can
+    Expression(String),
+    // one side of a multiple assignment e.g. `@a, @b = ...`
+    Unknown,
+}
+
+impl Value {
+    pub fn as_receiver(&self) -> Option<&Receiver> {
+        match self {
+            Value::Receiver(receiver) => Some(receiver),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReturnValue {
+    // the literal's type e.g. `string` for `"Draft"` or `null` for `nil`
+    Literal(String),
+    Value(Receiver),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Status {
+    Code(u16),
+    // set from a varaible or expression e.g. `render json: x, status: status`
+    Dynamic(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResponseBody {
+    Json(Receiver),
+    Xml(Receiver),
+    Plain(Receiver),
+    Html(Receiver),
+    // name of the template rendered, empty for the action's own template
+    Template(String),
+    Redirect(Receiver),
+    Nothing,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Response {
+    pub status: Status,
+    pub content_type: Option<String>,
+    pub body: ResponseBody,
+    // set inside `respond_to` e.g. `format.json { ... }` is `json`
+    pub format: Option<String>,
+    // exceptions whose `rescue_from` handler sends this response
+    pub exception: Option<String>,
+    // an exception nothing rescues, sent with rails' default status for it
+    pub unrescued: Option<String>,
+    // `serializer:` or `each_serializer:` given to `render json:`
+    pub serializer: Option<String>,
+    // shape of `render json:` when it can be worked out e.g. from a hash literal
+    pub shape: Option<JsonValue>,
+}
 
-    // method name and method indexes
-    pub method_calls: Vec<(String, Vec<String>)>, // is nearly done
-    pub renders: Vec<String>,                     // TODO: implement this one
+// what a value looks like once rendered as json
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonValue {
+    Object(Vec<(String, JsonValue)>),
+    Array(Box<JsonValue>),
+    // `as_json`/`to_json` on a record, without `only:` every column is sent
+    Record {
+        value: Receiver,
+        only: Option<Vec<String>>,
+        except: Vec<String>,
+        // `methods:` and `include:`
+        extra: Vec<(String, JsonValue)>,
+    },
+    // anything else e.g. `@user.id`
+    Value(Receiver),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MethodCall {
+    pub name: String,
+    pub receiver: Receiver,
+    pub args: Vec<String>,
+    // keyword args e.g. `render(user, view: :extended)` has `("view", "extended")`
+    pub options: Vec<(String, String)>,
+    // args as json so renders in the callee can be followed e.g. `json_ok({ id: 1 }, 200)`
+    pub arg_values: Vec<JsonValue>,
+}
+
+impl MethodCall {
+    pub fn get_option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Only calls sent to self can be resolved to a controller, concern or helper method
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self.receiver,
+            Receiver::ImplicitSelf | Receiver::ExplicitSelf
+        )
+    }
 }
+
 #[derive(Debug)]
 pub enum ActionKinds {
     BeforeAction,
     AroundAction,
-    RescueFrom,
     Custom(String),
 }
 
@@ -29,7 +171,14 @@ pub struct Controller {
     pub parent: String,
     pub methods: Vec<MethodDetails>,
     pub actions: Vec<(ActionKinds, String)>,
+    pub rescues: Vec<RescueHandler>,
     pub include: Vec<String>,
+    pub prepend: Vec<String>,
+    // `helper :all`, `helper :posts` and `helper PostsHelper` are `all`, `PostsHelper` and
+    // `PostsHelper`
+    pub helpers: Vec<String>,
+    // controller methods `helper_method` lets views call
+    pub helper_methods: Vec<String>,
     pub module: Option<String>,
     // ignoring requires for now
 }
@@ -45,6 +194,15 @@ pub struct Concern {
     pub name: String,
     pub methods: Vec<MethodDetails>,
     pub actions: Vec<(ActionKinds, String)>,
+    pub rescues: Vec<RescueHandler>,
+}
+
+// `rescue_from ActiveRecord::RecordNotFound, with: :not_found`, the block and lambda forms are
+// kept as a private method named after the call e.g. `rescue_from ActiveRecord::RecordNotFound`
+#[derive(Debug, PartialEq, Clone)]
+pub struct RescueHandler {
+    pub exceptions: Vec<String>,
+    pub method: String,
 }
 
 #[derive(Debug)]
@@ -52,8 +210,153 @@ pub struct AppData {
     pub concerns: HashMap<String, Concern>,
     pub helpers: HashMap<String, HelperModule>,
     pub controllers: HashMap<String, Controller>,
-    pub routes: HashMap<String, Request>,
+    pub routes: RouteTable,
+    // rack apps mounted in config/routes.rb
+    pub mounts: Vec<Mount>,
     pub views: HashMap<String, HashMap<String, View>>,
+    pub serializers: HashMap<String, Serializer>,
+    // table name to its columns from db/schema.rb
+    pub tables: HashMap<String, Table>,
+    pub models: HashMap<String, Model>,
+    // controller name to its resolved methods, see `AppData::build_method_tables`
+    pub method_tables: HashMap<String, MethodTable>,
+}
+
+// where a method was defined
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum MethodOwner {
+    Controller(String),
+    Concern(String),
+    Helper(String),
+}
+
+// index into the owner's `methods`, so tables don't need to clone any `MethodDetails`
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct MethodRef {
+    pub owner: MethodOwner,
+    pub index: usize,
+}
+
+/// Every method a controller can call, built once after parsing.
+#[derive(Debug, Default)]
+pub struct MethodTable {
+    // method resolution order, see `Controller::find_ancestors`
+    pub ancestors: Vec<MethodOwner>,
+    // method name to each definition of it in ancestor order, the first one is what gets called
+    pub methods: HashMap<String, Vec<MethodRef>>,
+    // memoized params for a method and everything it calls
+    params: RefCell<HashMap<MethodRef, HashSet<String>>>,
+}
+
+// `attributes :id, :name` or `attribute :name, if: :admin?` in an ActiveModel::Serializer
+#[derive(Debug, PartialEq, Clone)]
+pub struct SerializerAttribute {
+    pub name: String,
+    // only sent sometimes e.g. `if:` or `unless:`
+    pub optional: bool,
+}
+
+// `has_many :posts, serializer: PostSerializer`, `has_one` and `belongs_to` or a Blueprinter
+// `association :posts, blueprint: PostBlueprint, view: :normal`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Association {
+    pub name: String,
+    pub many: bool,
+    pub serializer: Option<String>,
+    // Blueprinter view of the associated blueprint
+    pub view: Option<String>,
+    pub optional: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum SerializerKind {
+    #[default]
+    ActiveModel,
+    // `include JSONAPI::Serializer` or `include FastJsonapi::ObjectSerializer`
+    JsonApi,
+    // `class UserBlueprint < Blueprinter::Base`
+    Blueprinter,
+}
+
+// everything a serializer or one of its Blueprinter views declares
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SerializerFields {
+    pub attributes: Vec<SerializerAttribute>,
+    pub associations: Vec<Association>,
+    // `include_view :normal`
+    pub include_views: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Serializer {
+    pub name: String,
+    pub parent: String,
+    pub kind: SerializerKind,
+    // `identifier :id` in Blueprinter and `set_id :slug` in JSON:API
+    pub identifier: Option<String>,
+    pub fields: SerializerFields,
+    // Blueprinter `view :extended do ... end`
+    pub views: HashMap<String, SerializerFields>,
+}
+
+/// A column from db/schema.rb
+#[derive(Debug, PartialEq, Clone)]
+pub struct Column {
+    pub name: String,
+    pub sql_type: String,
+    pub null: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+}
+
+/// `belongs_to`, `has_one` or `has_many` on a model
+#[derive(Debug, PartialEq, Clone)]
+pub struct ModelAssociation {
+    pub name: String,
+    pub class_name: String,
+    pub many: bool,
+    // `belongs_to ..., optional: true` and every `has_one`
+    pub optional: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Model {
+    pub name: String,
+    pub parent: String,
+    pub table_name: String,
+    pub associations: Vec<ModelAssociation>,
+}
+
+/// The type of a view's field once its value has been followed back to a model
+#[derive(Debug, PartialEq, Clone)]
+pub enum FieldType {
+    Column {
+        sql_type: String,
+        null: bool,
+    },
+    // a helper returning a literal e.g. `string` for `status_label(@post)`
+    Literal {
+        name: String,
+        null: bool,
+    },
+    Model {
+        name: String,
+        many: bool,
+        null: bool,
+    },
+    Unknown,
+}
+
+/// Where a view reads a key's value from, `@post.editor.name` is `@post` then editor and name.
+/// Roots without an `@` are helper methods e.g. `avatar_url(@user)` is `avatar_url`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValueSource {
+    pub root: String,
+    pub path: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -61,87 +364,695 @@ pub enum ViewType {
     Jbuilder,
     Jb,
 }
+/// One branch of a conditional in a view and the keys it renders
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewBranch {
+    pub condition: String,
+    pub keys: Vec<String>,
+}
+
+/// The mutually exclusive branches of an `if`, `unless`, ternary or `case` in a view
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewConditional {
+    pub branches: Vec<ViewBranch>,
+}
+
+impl ViewConditional {
+    // more than one branch renders something and they disagree, so the shapes are alternatives
+    pub fn is_one_of(&self) -> bool {
+        let shapes: Vec<&Vec<String>> = self
+            .branches
+            .iter()
+            .map(|x| &x.keys)
+            .filter(|x| !x.is_empty())
+            .collect();
+        shapes.len() > 1 && shapes.iter().any(|x| x != &shapes[0])
+    }
+}
+
 // response is a vector for conditional responses
 #[derive(Debug)]
 pub struct View {
+    // directory under app/views e.g. `api/v1/users`
     pub controller: String,
+    // the template's filename
     pub method: String,
+    pub format: Option<String>,
+    pub variant: Option<String>,
     pub response: Vec<String>,
+    pub conditionals: Vec<ViewConditional>,
+    // keys, without any `?`, to where their value comes from
+    pub sources: HashMap<String, ValueSource>,
+    // methods the view calls without a receiver, sorted
+    pub helper_calls: Vec<String>,
     pub view_type: ViewType,
+    // instance varaibles read by the view
+    pub instance_varaibles: HashSet<String>,
 }
 
-impl Controller {
-    pub fn get_own_methods(&self) -> Vec<MethodDetails> {
-        self.methods.clone()
+/// Instance varaibles a route's callbacks and action assign compared to what its view reads
+#[derive(Debug, Default)]
+pub struct IvarFlow {
+    pub assigned: HashMap<String, Vec<Value>>,
+    pub read: HashSet<String>,
+}
+
+impl IvarFlow {
+    // read by the view but nothing assigns them, so they will always be nil
+    pub fn get_unassigned(&self) -> Vec<&String> {
+        let mut results: Vec<&String> = self
+            .read
+            .iter()
+            .filter(|x| !self.assigned.contains_key(*x))
+            .collect();
+        results.sort();
+        results
     }
 
-    pub fn get_inherited_methods(&self, app_data: &AppData) -> Vec<MethodDetails> {
-        match app_data.controllers.get(&self.parent) {
-            Some(parent_controller) => parent_controller.get_all_methods(app_data),
-            None => Vec::new(),
+    pub fn get_unrendered(&self) -> Vec<&String> {
+        let mut results: Vec<&String> = self
+            .assigned
+            .keys()
+            .filter(|x| !self.read.contains(*x))
+            .collect();
+        results.sort();
+        results
+    }
+}
+
+impl AppData {
+    pub fn new(routes: Vec<Request>) -> Self {
+        AppData {
+            concerns: HashMap::new(),
+            helpers: HashMap::new(),
+            controllers: HashMap::new(),
+            routes: RouteTable::new(routes),
+            mounts: Vec::new(),
+            views: HashMap::new(),
+            serializers: HashMap::new(),
+            tables: HashMap::new(),
+            models: HashMap::new(),
+            method_tables: HashMap::new(),
         }
     }
 
-    pub fn get_included_methods(&self, app_data: &AppData) -> Vec<MethodDetails> {
-        let mut methods: Vec<MethodDetails> = Vec::new();
-        for included in &self.include {
-            let mut include_found = false;
-            if let Some(con) = app_data.concerns.get(included) {
-                methods.append(&mut con.methods.clone());
-                include_found = true;
-            }
+    pub fn get_methods(&self, owner: &MethodOwner) -> &[MethodDetails] {
+        match owner {
+            MethodOwner::Controller(name) => self.controllers.get(name).map(|x| &x.methods[..]),
+            MethodOwner::Concern(name) => self.concerns.get(name).map(|x| &x.methods[..]),
+            MethodOwner::Helper(name) => self.helpers.get(name).map(|x| &x.methods[..]),
+        }
+        .unwrap_or(&[])
+    }
+
+    pub fn get_method(&self, method: &MethodRef) -> Option<&MethodDetails> {
+        self.get_methods(&method.owner).get(method.index)
+    }
 
-            if let Some(hel) = app_data.helpers.get(included) {
-                methods.append(&mut hel.methods.clone());
-                include_found = true;
+    pub fn get_actions(&self, owner: &MethodOwner) -> &[(ActionKinds, String)] {
+        match owner {
+            MethodOwner::Controller(name) => self.controllers.get(name).map(|x| &x.actions[..]),
+            MethodOwner::Concern(name) => self.concerns.get(name).map(|x| &x.actions[..]),
+            MethodOwner::Helper(_) => None,
+        }
+        .unwrap_or(&[])
+    }
+
+    pub fn get_rescues(&self, owner: &MethodOwner) -> &[RescueHandler] {
+        match owner {
+            MethodOwner::Controller(name) => self.controllers.get(name).map(|x| &x.rescues[..]),
+            MethodOwner::Concern(name) => self.concerns.get(name).map(|x| &x.rescues[..]),
+            MethodOwner::Helper(_) => None,
+        }
+        .unwrap_or(&[])
+    }
+
+    // needs to be called again if any controllers, concerns or helpers are added
+    pub fn build_method_tables(&mut self) {
+        let tables = self
+            .controllers
+            .values()
+            .map(|controller| (controller.name.clone(), MethodTable::new(controller, self)))
+            .collect();
+        self.method_tables = tables;
+    }
+}
+
+impl MethodTable {
+    fn new(controller: &Controller, app_data: &AppData) -> Self {
+        let mut table = MethodTable {
+            ancestors: controller.find_ancestors(app_data),
+            ..Default::default()
+        };
+        for owner in table.ancestors.clone() {
+            for (index, method) in app_data.get_methods(&owner).iter().enumerate() {
+                table.add(
+                    &method.name,
+                    MethodRef {
+                        owner: owner.clone(),
+                        index,
+                    },
+                );
             }
+        }
+
+        table
+    }
+
+    fn add(&mut self, name: &str, method: MethodRef) {
+        self.methods
+            .entry(name.to_owned())
+            .or_default()
+            .push(method);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MethodRef> {
+        self.methods.get(name)?.first()
+    }
 
-            if !include_found {
-                println!("WARNING: Include {} not found for {}", included, self.name);
+    // the definition `super` calls from inside of `method`
+    pub fn get_super(&self, name: &str, method: &MethodRef) -> Option<&MethodRef> {
+        let definitions = self.methods.get(name)?;
+        let position = definitions.iter().position(|x| x == method)?;
+        definitions.get(position + 1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MethodRef> {
+        self.methods.values().flatten()
+    }
+
+    /// Params used by a method along with every method it calls.
+    pub fn get_params(&self, method: &MethodRef, app_data: &AppData) -> HashSet<String> {
+        if let Some(params) = self.params.borrow().get(method) {
+            return params.clone();
+        }
+
+        // only complete results are memoized, anything part way through this search
+        // could be missing params from a method further up a recursive loop
+        let mut params = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut buf = vec![method.clone()];
+        while let Some(current) = buf.pop() {
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            if let Some(memoized) = self.params.borrow().get(&current) {
+                params.extend(memoized.iter().cloned());
+                continue;
+            }
+            if let Some(details) = app_data.get_method(&current) {
+                params.extend(details.params.iter().cloned());
+                buf.append(&mut self.get_callees(details, &current));
             }
         }
 
-        methods
+        self.params
+            .borrow_mut()
+            .insert(method.clone(), params.clone());
+        params
     }
 
-    pub fn get_all_methods(&self, app_data: &AppData) -> Vec<MethodDetails> {
-        let mut methods = self.get_own_methods();
-        methods.append(&mut self.get_inherited_methods(app_data));
-        methods.append(&mut self.get_included_methods(app_data));
+    // methods that `details` can call directly
+    fn get_callees(&self, details: &MethodDetails, method: &MethodRef) -> Vec<MethodRef> {
+        let mut callees: Vec<MethodRef> = details
+            .method_calls
+            .iter()
+            .filter(|call| call.is_local())
+            .filter_map(|call| self.get(&call.name))
+            .cloned()
+            .collect();
+        if details.calls_super {
+            if let Some(sub) = self.get_super(&details.name, method) {
+                callees.push(sub.clone());
+            }
+        }
+        callees
+    }
 
-        methods
+    /// Every method that can be reached by calling `method`, including itself
+    pub fn get_reachable(&self, method: &MethodRef, app_data: &AppData) -> HashSet<MethodRef> {
+        let mut visited = HashSet::new();
+        let mut buf = vec![method.clone()];
+        while let Some(current) = buf.pop() {
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            if let Some(details) = app_data.get_method(&current) {
+                buf.append(&mut self.get_callees(details, &current));
+            }
+        }
+
+        visited
     }
+}
 
-    pub fn get_method_by_name(&self, name: &str, app_data: &AppData) -> Option<MethodDetails> {
-        for method in self.get_all_methods(app_data) {
-            if method.name == name {
-                return Some(method);
+impl Controller {
+    /// The superclass, looked up from the innermost module the class is nested in outwards the
+    /// way ruby resolves constants e.g. `BaseController` in `module Api` is `Api::BaseController`
+    pub fn get_parent<'a>(&self, app_data: &'a AppData) -> Option<&'a Controller> {
+        let mut module = self.module.clone().unwrap_or_default();
+        loop {
+            let name = if module.is_empty() {
+                self.parent.clone()
+            } else {
+                format!("{}::{}", module, self.parent)
+            };
+            if let Some(parent) = app_data.controllers.get(&name) {
+                return Some(parent);
+            }
+            match module.rsplit_once("::") {
+                Some((outer, _)) => module = outer.to_owned(),
+                None if !module.is_empty() => module.clear(),
+                None => return None,
             }
         }
+    }
 
-        None
+    pub fn get_method_table<'a>(&self, app_data: &'a AppData) -> Option<&'a MethodTable> {
+        app_data.method_tables.get(&self.name)
     }
 
-    pub fn get_method_params(&self, method: &MethodDetails, app_data: &AppData) -> HashSet<String> {
-        let mut params = method.params.clone();
-        for (sub_name, _) in &method.method_calls {
-            if let Some(sub) = self.get_method_by_name(sub_name, app_data) {
-                // Currently we can't distinguish between
-                //   def has_permission?(permission)
-                //     @user.has_permission?(permission)
-                //   end
-                if sub.method_calls != method.method_calls && method.args != sub.args {
-                    params.extend(self.get_method_params(&sub, app_data));
-                }
+    /// Ruby's ancestors for the controller: prepended modules (latest first), the class itself,
+    /// included modules (latest first) and then the same again for each superclass.
+    pub fn find_ancestors(&self, app_data: &AppData) -> Vec<MethodOwner> {
+        let mut ancestors = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = Some(self);
+        while let Some(con) = current {
+            if !visited.insert(con.name.clone()) {
+                println!("WARNING: {} inherits from itself", self.name);
+                break;
             }
-            // else {
-            //     println!(
-            //         "WARNING: no details found for {} in controller {}",
-            //         sub_name, self.name
-            //     );
-            // }
+            for prepended in con.prepend.iter().rev() {
+                ancestors.append(&mut con.find_modules(prepended, app_data));
+            }
+            ancestors.push(MethodOwner::Controller(con.name.clone()));
+            for included in con.include.iter().rev() {
+                ancestors.append(&mut con.find_modules(included, app_data));
+            }
+            current = con.get_parent(app_data);
         }
 
-        params
+        // including a module a superclass already has is a no-op in ruby
+        let mut seen = HashSet::new();
+        let mut deduped = ancestors
+            .into_iter()
+            .rev()
+            .filter(|owner| seen.insert(owner.clone()))
+            .collect::<Vec<MethodOwner>>();
+        deduped.reverse();
+        deduped
+    }
+
+    fn find_modules(&self, name: &str, app_data: &AppData) -> Vec<MethodOwner> {
+        let mut modules = Vec::new();
+        if app_data.concerns.contains_key(name) {
+            modules.push(MethodOwner::Concern(name.to_owned()));
+        }
+        if app_data.helpers.contains_key(name) {
+            modules.push(MethodOwner::Helper(name.to_owned()));
+        }
+        if modules.is_empty() {
+            println!("WARNING: Include {} not found for {}", name, self.name);
+        }
+        modules
+    }
+
+    /// Callbacks from every ancestor, superclasses first as that is the order rails runs them in
+    pub fn get_callbacks<'a>(&self, app_data: &'a AppData) -> Vec<&'a (ActionKinds, String)> {
+        match self.get_method_table(app_data) {
+            Some(table) => table
+                .ancestors
+                .iter()
+                .rev()
+                .flat_map(|owner| app_data.get_actions(owner))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // rails checks the handlers declared last first, so the most specific come first
+    pub fn get_rescue_handlers<'a>(&self, app_data: &'a AppData) -> Vec<&'a RescueHandler> {
+        match self.get_method_table(app_data) {
+            Some(table) => table
+                .ancestors
+                .iter()
+                .flat_map(|owner| app_data.get_rescues(owner).iter().rev())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_own_methods(&self) -> Vec<&MethodDetails> {
+        self.methods.iter().collect()
+    }
+
+    fn get_methods_where<'a>(
+        &self,
+        app_data: &'a AppData,
+        predicate: impl Fn(&MethodOwner) -> bool,
+    ) -> Vec<&'a MethodDetails> {
+        match self.get_method_table(app_data) {
+            Some(table) => table
+                .iter()
+                .filter(|method| predicate(&method.owner))
+                .filter_map(|method| app_data.get_method(method))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_inherited_methods<'a>(&self, app_data: &'a AppData) -> Vec<&'a MethodDetails> {
+        self.get_methods_where(app_data, |owner| match owner {
+            MethodOwner::Controller(name) => name != &self.name,
+            _ => false,
+        })
+    }
+
+    pub fn get_included_methods<'a>(&self, app_data: &'a AppData) -> Vec<&'a MethodDetails> {
+        self.get_methods_where(app_data, |owner| {
+            !matches!(owner, MethodOwner::Controller(_))
+        })
+    }
+
+    pub fn get_method_by_name<'a>(
+        &self,
+        name: &str,
+        app_data: &'a AppData,
+    ) -> Option<&'a MethodDetails> {
+        app_data.get_method(self.get_method_table(app_data)?.get(name)?)
+    }
+
+    /// Public methods that a route can point at, rails calls these `action_methods`
+    pub fn get_action_methods<'a>(&self, app_data: &'a AppData) -> Vec<&'a MethodDetails> {
+        let Some(table) = self.get_method_table(app_data) else {
+            return Vec::new();
+        };
+        // ancestor then declaration order, so the output is the same every run
+        let mut seen = HashSet::new();
+        table
+            .ancestors
+            .iter()
+            .flat_map(|owner| app_data.get_methods(owner))
+            .filter(|method| seen.insert(method.name.as_str()))
+            .filter_map(|method| self.get_method_by_name(&method.name, app_data))
+            .filter(|method| method.visibility == Visibility::Public && !method.class_method)
+            .collect()
+    }
+
+    pub fn get_method_params(&self, name: &str, app_data: &AppData) -> Option<HashSet<String>> {
+        let table = self.get_method_table(app_data)?;
+        Some(table.get_params(table.get(name)?, app_data))
+    }
+}
+
+#[cfg(test)]
+mod method_table_tests {
+    use pretty_assertions::assert_eq;
+
+    use super::MethodOwner;
+    use crate::app_data_from_source;
+
+    fn params_helper(sources: &[&str], controller: &str, action: &str) -> String {
+        let app_data = app_data_from_source(sources);
+        let mut results = app_data.controllers[controller]
+            .get_method_params(action, &app_data)
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<String>>();
+        results.sort();
+        results.join(", ")
+    }
+
+    #[test]
+    fn nested_calls() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+                user_details
+            end
+
+            def user_details
+                User.find(params[:user_id]).where(page: page_number)
+            end
+
+            def page_number
+                params[:page]
+            end
+        end
+        ";
+        assert_eq!(
+            params_helper(&[input], "PagesController", "index"),
+            "page, user_id"
+        );
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+                ping
+            end
+
+            def ping
+                pong if params[:ping]
+            end
+
+            def pong
+                ping if params[:pong]
+            end
+        end
+        ";
+        assert_eq!(
+            params_helper(&[input], "PagesController", "index"),
+            "ping, pong"
+        );
+        assert_eq!(
+            params_helper(&[input], "PagesController", "pong"),
+            "ping, pong"
+        );
+    }
+
+    #[test]
+    fn inherited_and_included() {
+        let application = "
+        class ApplicationController < ActionController::API
+            include PageHelper
+
+            def auth_check
+                params[:auth_token]
+            end
+        end
+        ";
+        let helper = "
+        module PageHelper
+            def blog_category
+                Blogs.find(params[:cat])
+            end
+        end
+        ";
+        let pages = "
+        class PagesController < ApplicationController
+            def index
+                auth_check
+                blog_category
+            end
+        end
+        ";
+        assert_eq!(
+            params_helper(&[application, helper, pages], "PagesController", "index"),
+            "auth_token, cat"
+        );
+    }
+
+    #[test]
+    fn calls_with_receivers_are_not_resolved() {
+        let input = "
+        class PagesController < ApplicationController
+            def index
+                @user.count
+            end
+
+            def count
+                params[:count]
+            end
+        end
+        ";
+        assert_eq!(params_helper(&[input], "PagesController", "index"), "");
+    }
+
+    #[test]
+    fn later_includes_take_precedence() {
+        let first = "
+        module First
+            extend ActiveSupport::Concern
+
+            def lookup
+                params[:first]
+            end
+        end
+        ";
+        let second = "
+        module Second
+            extend ActiveSupport::Concern
+
+            def lookup
+                params[:second]
+            end
+        end
+        ";
+        let pages = "
+        class PagesController < ApplicationController
+            include First
+            include Second
+
+            def index
+                lookup
+            end
+        end
+        ";
+        assert_eq!(
+            params_helper(&[first, second, pages], "PagesController", "index"),
+            "second"
+        );
+    }
+
+    #[test]
+    fn included_modules_come_before_superclass() {
+        let application = "
+        class ApplicationController < ActionController::API
+            def lookup
+                params[:application]
+            end
+        end
+        ";
+        let concern = "
+        module Lookup
+            extend ActiveSupport::Concern
+
+            def lookup
+                params[:concern]
+            end
+        end
+        ";
+        let pages = "
+        class PagesController < ApplicationController
+            include Lookup
+
+            def index
+                lookup
+            end
+        end
+        ";
+        assert_eq!(
+            params_helper(&[application, concern, pages], "PagesController", "index"),
+            "concern"
+        );
+    }
+
+    #[test]
+    fn super_follows_ancestors() {
+        let application = "
+        class ApplicationController < ActionController::API
+            def show
+                params[:application]
+            end
+        end
+        ";
+        let prepended = "
+        module Audit
+            extend ActiveSupport::Concern
+
+            def show
+                super(params[:audit])
+            end
+        end
+        ";
+        let concern = "
+        module Lookup
+            extend ActiveSupport::Concern
+
+            def show
+                params[:concern]
+                super
+            end
+        end
+        ";
+        let pages = "
+        class PagesController < ApplicationController
+            prepend Audit
+            include Lookup
+
+            def show
+                params[:pages]
+                super
+            end
+        end
+        ";
+        let app_data = app_data_from_source(&[application, prepended, concern, pages]);
+        assert_eq!(
+            app_data.method_tables["PagesController"].ancestors,
+            [
+                MethodOwner::Concern("Audit".to_owned()),
+                MethodOwner::Controller("PagesController".to_owned()),
+                MethodOwner::Concern("Lookup".to_owned()),
+                MethodOwner::Controller("ApplicationController".to_owned()),
+            ]
+        );
+        assert_eq!(
+            params_helper(
+                &[application, prepended, concern, pages],
+                "PagesController",
+                "show"
+            ),
+            "application, audit, concern, pages"
+        );
+    }
+
+    #[test]
+    fn callbacks_from_ancestors() {
+        let application = "
+        class ApplicationController < ActionController::API
+            include ErrorHandling
+
+            before_action :auth_check
+
+            def auth_check
+                params[:auth_token]
+            end
+        end
+        ";
+        let concern = "
+        module ErrorHandling
+            extend ActiveSupport::Concern
+
+            included do
+                around_action :catch_exceptions
+            end
+
+            def catch_exceptions
+                yield
+            end
+        end
+        ";
+        let pages = "
+        class PagesController < ApplicationController
+            before_action :get_page_number
+
+            def get_page_number
+                params[:index]
+            end
+        end
+        ";
+        let app_data = app_data_from_source(&[application, concern, pages]);
+        assert_eq!(
+            app_data.controllers["PagesController"]
+                .get_callbacks(&app_data)
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<&str>>(),
+            ["catch_exceptions", "auth_check", "get_page_number"]
+        );
     }
 }
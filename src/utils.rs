@@ -1,4 +1,5 @@
 use lib_ruby_parser::Node;
+use walkdir::DirEntry;
 
 pub fn parse_node_str(node: &Node) -> String {
     match node {
@@ -22,7 +23,7 @@ pub fn parse_node_str(node: &Node) -> String {
                     array
                         .elements
                         .iter()
-                        .map(|x| parse_node_str(x))
+                        .map(parse_node_str)
                         .collect::<Vec<String>>()
                         .join(",")
                 )
@@ -36,7 +37,7 @@ pub fn parse_node_str(node: &Node) -> String {
                     "{{{}}}",
                     hash.pairs
                         .iter()
-                        .map(|x| parse_node_str(x))
+                        .map(parse_node_str)
                         .collect::<Vec<String>>()
                         .join(",")
                 )
@@ -46,7 +47,7 @@ pub fn parse_node_str(node: &Node) -> String {
         Node::Kwargs(kwargs) => kwargs
             .pairs
             .iter()
-            .map(|kwarg| parse_node_str(kwarg))
+            .map(parse_node_str)
             .collect::<Vec<String>>()
             .join(","),
         Node::Const(node_const_name) => {
@@ -78,7 +79,7 @@ pub fn parse_node_str(node: &Node) -> String {
             parse_node_str(&stat.recv),
             stat.indexes
                 .iter()
-                .map(|x| parse_node_str(x))
+                .map(parse_node_str)
                 .collect::<Vec<String>>()
                 .join(",")
         ),
@@ -92,7 +93,7 @@ pub fn parse_name(node: Node) -> String {
 
 pub fn parse_superclass(node: Option<Box<Node>>) -> String {
     if let Some(boxed_node) = node {
-        parse_node_str(&*boxed_node)
+        parse_node_str(&boxed_node)
     } else {
         "".to_string()
     }
@@ -114,3 +115,54 @@ pub fn get_node_name(name: &Node) -> Result<String, String> {
         _ => Err("could not get name".to_string()),
     }
 }
+
+// good enough for model names e.g. `posts` is `post` and `categories` is `category`
+pub fn singularize(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if ["sses", "shes", "ches", "xes", "uses"]
+        .iter()
+        .any(|x| word.ends_with(x))
+    {
+        word[..word.len() - 2].to_owned()
+    } else if word.ends_with("ss") {
+        word.to_owned()
+    } else {
+        word.strip_suffix('s').unwrap_or(word).to_owned()
+    }
+}
+
+// the inverse of `singularize` e.g. `category` is `categories`, used for table names
+pub fn pluralize(word: &str) -> String {
+    let vowel_y = ["ay", "ey", "iy", "oy", "uy"]
+        .iter()
+        .any(|x| word.ends_with(x));
+    if word.ends_with('y') && !vowel_y {
+        format!("{}ies", &word[..word.len() - 1])
+    } else if ["s", "sh", "ch", "x", "z"]
+        .iter()
+        .any(|x| word.ends_with(x))
+    {
+        format!("{}es", word)
+    } else {
+        format!("{}s", word)
+    }
+}
+
+// 1 based line number for a byte offset into the source
+pub fn line_number(source: &[u8], offset: usize) -> usize {
+    source[..offset.min(source.len())]
+        .iter()
+        .filter(|x| **x == b'\n')
+        .count()
+        + 1
+}
+
+// dot files and folders like `.git` are skipped when walking the app
+pub fn is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
+}
@@ -1,195 +1,897 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet},
     fs,
     path::Path,
 };
 
-use lib_ruby_parser::{Node, Parser, ParserResult};
-use walkdir::{DirEntry, WalkDir};
+use convert_case::{Case, Casing};
+use lib_ruby_parser::{
+    nodes::{Ivar, Send},
+    traverse::Visitor,
+    Node, Parser, ParserResult,
+};
+use walkdir::WalkDir;
 
 use crate::{
-    types::{View, ViewType},
+    params::parse_receiver,
+    responses,
+    types::{ValueSource, View, ViewBranch, ViewConditional, ViewType},
     utils,
 };
 
-fn parse_jbuiler_nodes(node: &Node, optional: bool, parent: &str) -> Vec<String> {
-    let mut results = Vec::new();
-    let mut buf = VecDeque::new();
-    buf.push_back(node);
-    while let Some(temp) = buf.pop_front() {
-        match temp {
-            Node::Begin(begin) => {
-                for arg in &begin.statements {
-                    buf.push_back(arg);
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyTransform {
+    // `camelize: :lower` is true
+    Camelize(bool),
+    Dasherize,
+    Upcase,
+}
+
+/// How jbuilder formats keys, set for the app by `Jbuilder.key_format camelize: :lower` and for a
+/// view by `json.key_format!`. Merged hashes are only formatted with `deep_format_keys`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyFormat {
+    pub transforms: Vec<KeyTransform>,
+    pub deep: bool,
+}
+
+impl KeyFormat {
+    pub fn apply(&self, key: &str) -> String {
+        self.transforms
+            .iter()
+            .fold(key.to_owned(), |key, transform| match transform {
+                KeyTransform::Camelize(true) => key.to_case(Case::Camel),
+                KeyTransform::Camelize(false) => key.to_case(Case::Pascal),
+                KeyTransform::Dasherize => key.replace('_', "-"),
+                KeyTransform::Upcase => key.to_uppercase(),
+            })
+    }
+
+    // `merge!` keys, which may be nested e.g. `meta.view_count`
+    fn apply_deep(&self, key: &str) -> String {
+        if self.deep {
+            key.split('.')
+                .map(|x| self.apply(x))
+                .collect::<Vec<_>>()
+                .join(".")
+        } else {
+            key.to_owned()
+        }
+    }
+}
+
+// `key_format :upcase` or `key_format camelize: :lower`
+fn parse_key_transforms(args: &[Node]) -> Vec<KeyTransform> {
+    let transform = |name: &str, lower: bool| match name {
+        "camelize" => Some(KeyTransform::Camelize(lower)),
+        "dasherize" => Some(KeyTransform::Dasherize),
+        "upcase" => Some(KeyTransform::Upcase),
+        _ => None,
+    };
+    args.iter()
+        .flat_map(|arg| match arg {
+            Node::Sym(sym) => transform(&sym.name.to_string_lossy(), false)
+                .into_iter()
+                .collect(),
+            Node::Kwargs(_) | Node::Hash(_) => responses::options(std::slice::from_ref(arg))
+                .iter()
+                .filter_map(|(name, value)| {
+                    transform(name, symbol_name(value).as_deref() == Some("lower"))
+                })
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+// `deep_format_keys!` and `deep_format_keys!(false)`
+fn parse_deep_format(args: &[Node]) -> bool {
+    !matches!(args.first(), Some(Node::False(_)))
+}
+
+#[derive(Default)]
+struct KeyFormatFinder {
+    key_format: KeyFormat,
+}
+
+impl Visitor for KeyFormatFinder {
+    fn on_send(&mut self, node: &Send) {
+        if let Some(Node::Const(recv)) = node.recv.as_deref() {
+            if recv.name == "Jbuilder" && recv.scope.is_none() {
+                match node.method_name.as_str() {
+                    "key_format" => self.key_format.transforms = parse_key_transforms(&node.args),
+                    "deep_format_keys" => self.key_format.deep = parse_deep_format(&node.args),
+                    _ => {}
                 }
             }
-            Node::Block(begin) => {
-                if let Node::Send(stat) = *begin.call.clone() {
-                    if !stat.method_name.is_empty() && parent.ends_with(&stat.method_name) {
-                        for arg in &begin.args {
-                            buf.push_back(arg);
-                        }
-                        for arg in &begin.body {
-                            buf.push_back(arg);
-                        }
-                    } else {
-                        results.append(&mut parse_jbuiler_nodes(
-                            temp,
-                            optional,
-                            &(parent.to_owned() + &stat.method_name.clone()),
-                        ))
-                    }
-                } else {
-                    buf.push_back(&*begin.call);
-                    for arg in &begin.args {
-                        buf.push_back(arg);
-                    }
-                    for arg in &begin.body {
-                        buf.push_back(arg);
-                    }
-                }
+        }
+        self.maybe_visit(&node.recv);
+        self.visit_all(&node.args);
+    }
+}
+
+/// Reads the `Jbuilder.key_format` and `Jbuilder.deep_format_keys` app wide settings, usually
+/// in config/initializers
+pub fn parse_key_format(path: &Path) -> Result<KeyFormat, Box<dyn std::error::Error>> {
+    let mut finder = KeyFormatFinder::default();
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| -> bool { !utils::is_hidden(e) })
+        .filter_map(|e| e.ok())
+    {
+        let f = entry.path();
+        if f.is_file() && f.display().to_string().ends_with(".rb") {
+            if let Some(ast) = Parser::new(&fs::read(f)?, Default::default())
+                .do_parse()
+                .ast
+            {
+                finder.visit(&ast);
             }
-            Node::If(stat) => {
-                stat.if_true.iter().for_each(|b| {
-                    results.append(&mut parse_jbuiler_nodes(b, true, &(*parent).to_owned()))
-                });
-                stat.if_false.iter().for_each(|a| {
-                    results.append(&mut parse_jbuiler_nodes(a, false, &(*parent).to_owned()))
-                });
+        }
+    }
+    Ok(finder.key_format)
+}
+
+// joins a key onto its parent, `?` marks keys that may be missing
+fn join_key(parent: &str, optional: bool, key: &str) -> String {
+    let prefix = if optional { "?" } else { "" };
+    if parent.is_empty() {
+        format!("{}{}", prefix, key)
+    } else {
+        format!("{}.{}{}", parent, prefix, key)
+    }
+}
+
+// array elements are wrapped e.g. `json.array! @posts, :id` is `[id]`
+fn join_elements(parent: &str, optional: bool, elements: Vec<String>) -> Vec<String> {
+    if elements.is_empty() {
+        return vec![join_key(parent, optional, "[]")];
+    }
+    elements
+        .iter()
+        .map(|x| join_key(parent, optional, &format!("[{}]", x)))
+        .collect()
+}
+
+// what walking a template collects besides its keys
+#[derive(Default)]
+struct JbuilderContext {
+    conditionals: Vec<ViewConditional>,
+    // keys, without any `?`, to where their value comes from
+    sources: Vec<(String, ValueSource)>,
+    // block arguments to the collection they iterate e.g. `|upload|` is `@data.uploads`
+    locals: HashMap<String, ValueSource>,
+}
+
+impl JbuilderContext {
+    // follows attribute reads e.g. `@post.editor&.name` back to an ivar or a block argument
+    fn value_source(&self, node: &Node) -> Option<ValueSource> {
+        let (recv, name) = match node {
+            Node::Ivar(ivar) => {
+                return Some(ValueSource {
+                    root: ivar.name.clone(),
+                    path: Vec::new(),
+                })
             }
-            Node::Send(stat) => {
-                let prefix = if optional { "?" } else { "" }.to_string();
-                for arg in &stat.args {
-                    if let Node::Ivar(_) = arg {
-                    } else {
-                        let temp = utils::parse_node_str(arg);
-                        if temp == "unknown" {
-                            buf.push_back(arg);
-                        } else if parent.is_empty() {
-                            results.push(format!("{}{}", prefix, &temp));
-                        } else {
-                            results.push(format!("{}.{}{}", parent, prefix, &temp));
-                        }
-                    }
-                }
-                if let Some(recv) = stat.recv.clone() {
-                    if let Node::Send(is_json) = *recv.clone() {
-                        if is_json.method_name == "json" && stat.method_name != "call" {
-                            if parent.is_empty() {
-                                results.push(format!("{}{}", prefix, stat.method_name));
-                            } else {
-                                results.push(format!("{}.{}{}", parent, prefix, stat.method_name));
-                            }
-                        }
-                    } else {
-                    }
-                    // else if let Node::Begin(_) = *recv.clone() {
-                    //     // results.append(&mut parse_jbuiler_nodes(
-                    //     //     &*(stat.recv.clone().unwrap()).clone(),
-                    //     //     optional,
-                    //     //     &(parent.to_owned() + &stat.method_name.clone()),
-                    //     // ))
-
-                    //     if !stat.method_name.is_empty() && parent.ends_with(&stat.method_name) {
-                    //         buf.push_back(value)
-                    //     } else {
-                    //         results.append(&mut parse_jbuiler_nodes(
-                    //             temp,
-                    //             optional,
-                    //             &(parent.to_owned() + &stat.method_name.clone()),
-                    //         ))
-                    //     }
-                    // }
+            Node::Lvar(lvar) => return self.locals.get(&lvar.name).cloned(),
+            Node::Begin(begin) if begin.statements.len() == 1 => {
+                return self.value_source(&begin.statements[0])
+            }
+            // a helper e.g. `avatar_url(@user)`
+            Node::Send(send) if send.recv.is_none() => {
+                return Some(ValueSource {
+                    root: send.method_name.clone(),
+                    path: Vec::new(),
+                })
+            }
+            Node::Send(send) if send.args.is_empty() => (send.recv.as_deref(), &send.method_name),
+            Node::CSend(send) if send.args.is_empty() => (Some(&*send.recv), &send.method_name),
+            _ => return None,
+        };
+        let mut source = self.value_source(recv?)?;
+        source.path.push(name.clone());
+        Some(source)
+    }
+
+    fn record(&mut self, key: &str, source: Option<ValueSource>) {
+        if let Some(source) = source {
+            self.sources.push((key.replace('?', ""), source));
+        }
+    }
+
+    // `json.(@post, :id)` reads `@post.id`
+    fn record_attributes(&mut self, keys: &[String], object: Option<&Node>, attributes: &[String]) {
+        let object = object.and_then(|x| self.value_source(x));
+        for (key, attribute) in keys.iter().zip(attributes) {
+            self.record(
+                key,
+                object.clone().map(|mut x| {
+                    x.path.push(attribute.clone());
+                    x
+                }),
+            );
+        }
+    }
+
+    fn bind(&mut self, args: Option<&Node>, collection: Option<&Node>) {
+        let name = match args {
+            Some(Node::Args(args)) => match args.args.first() {
+                Some(Node::Arg(arg)) => Some(&arg.name),
+                Some(Node::Procarg0(arg)) => match arg.args.first() {
+                    Some(Node::Arg(arg)) => Some(&arg.name),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(name) = name {
+            match collection.and_then(|x| self.value_source(x)) {
+                Some(source) => self.locals.insert(name.clone(), source),
+                None => self.locals.remove(name),
+            };
+        }
+    }
+}
+
+fn partial_marker(name: &str) -> String {
+    format!("partial!({})", name)
+}
+
+// `json.foo` but not `json.foo.bar` or `foo.bar`
+fn json_call(node: &Node) -> Option<&Send> {
+    match node {
+        Node::Send(send) => match send.recv.as_deref() {
+            Some(Node::Send(recv)) if recv.method_name == "json" && recv.recv.is_none() => {
+                Some(send)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn symbol_name(node: &Node) -> Option<String> {
+    match node {
+        Node::Sym(sym) => Some(sym.name.to_string_lossy()),
+        Node::Str(str) => Some(str.value.to_string_lossy()),
+        _ => None,
+    }
+}
+
+// the partial rendered by `partial: 'comments/comment'` and whether it is rendered once per
+// element of a `collection:`
+fn parse_partial(args: &[Node]) -> Option<(String, bool)> {
+    let options = responses::options(args);
+    let collection = options.iter().any(|(name, _)| name == "collection");
+    options
+        .iter()
+        .find(|(name, _)| name == "partial")
+        .and_then(|(_, value)| symbol_name(value))
+        .map(|name| (name, collection))
+}
+
+// `json.key value`, `json.key @collection, :id, :name` and `json.key do ... end`
+fn parse_jbuilder_value(
+    key: &str,
+    args: &[Node],
+    body: Option<&Node>,
+    optional: bool,
+    parent: &str,
+    format: &KeyFormat,
+    context: &mut JbuilderContext,
+) -> Vec<String> {
+    let scope = join_key(parent, optional, key);
+    match (body, parse_partial(args)) {
+        (Some(body), _) => {
+            match parse_jbuiler_nodes(body, false, &scope, &mut format.clone(), context) {
+                children if children.is_empty() => vec![scope],
+                children => children,
+            }
+        }
+        (None, Some((name, _))) if args.len() > 1 => {
+            vec![format!("{}.{}", scope, partial_marker(&name))]
+        }
+        (None, _) if args.len() > 1 => {
+            let attributes: Vec<String> = args[1..].iter().filter_map(symbol_name).collect();
+            let keys: Vec<String> = attributes
+                .iter()
+                .map(|x| format!("{}.{}", scope, format.apply(x)))
+                .collect();
+            context.record_attributes(&keys, args.first(), &attributes);
+            keys
+        }
+        _ => {
+            context.record(&scope, args.first().and_then(|x| context.value_source(x)));
+            vec![scope]
+        }
+    }
+}
+
+// the keys of each element `array!` and `child!` blocks render, including any conditionals
+fn parse_jbuilder_elements(
+    body: &Node,
+    optional: bool,
+    parent: &str,
+    format: &KeyFormat,
+    context: &mut JbuilderContext,
+) -> Vec<String> {
+    let conditionals = context.conditionals.len();
+    let sources = context.sources.len();
+    let elements = parse_jbuiler_nodes(body, false, "", &mut format.clone(), context);
+    for conditional in &mut context.conditionals[conditionals..] {
+        for branch in &mut conditional.branches {
+            branch.keys = join_elements(parent, optional, std::mem::take(&mut branch.keys));
+        }
+    }
+    for (key, _) in &mut context.sources[sources..] {
+        *key = join_elements(parent, false, vec![std::mem::take(key)]).remove(0);
+    }
+    join_elements(parent, optional, elements)
+}
+
+// blocks are scoped so a `key_format!` inside one only lasts until its end
+fn parse_jbuilder_call(
+    call: &Send,
+    body: Option<&Node>,
+    optional: bool,
+    parent: &str,
+    format: &KeyFormat,
+    context: &mut JbuilderContext,
+) -> Vec<String> {
+    let args = &call.args;
+    match call.method_name.as_str() {
+        // `json.(@post, :id, :title)` is shorthand for `json.extract! @post, :id, :title`
+        "call" | "extract!" => {
+            let attributes: Vec<String> = args.iter().skip(1).filter_map(symbol_name).collect();
+            let keys: Vec<String> = attributes
+                .iter()
+                .map(|x| join_key(parent, optional, &format.apply(x)))
+                .collect();
+            context.record_attributes(&keys, args.first(), &attributes);
+            keys
+        }
+        "array!" => match (body, parse_partial(args)) {
+            (Some(body), _) => parse_jbuilder_elements(body, optional, parent, format, context),
+            (None, Some((name, _))) => join_elements(parent, optional, vec![partial_marker(&name)]),
+            (None, None) => {
+                let attributes: Vec<String> = args.iter().skip(1).filter_map(symbol_name).collect();
+                let keys = join_elements(
+                    parent,
+                    optional,
+                    attributes.iter().map(|x| format.apply(x)).collect(),
+                );
+                if !attributes.is_empty() {
+                    context.record_attributes(&keys, args.first(), &attributes);
                 }
+                keys
             }
-            Node::Procarg0(_) => {}
-            Node::Args(args) => args.args.iter().for_each(|f| buf.push_back(f)),
-            Node::Ivar(_) => {}
-            // conditional send e.g. foo&.id
-            Node::CSend(_) => {}
-            _ => {
-                // panic!("{:?}", temp);
+        },
+        "child!" => match body {
+            Some(body) => parse_jbuilder_elements(body, optional, parent, format, context),
+            None => join_elements(parent, optional, Vec::new()),
+        },
+        "merge!" => args
+            .first()
+            .map(|x| responses::flatten_json_value(&responses::parse_json_value(x)))
+            .unwrap_or_default()
+            .iter()
+            .map(|x| join_key(parent, optional, &format.apply_deep(x)))
+            .collect(),
+        "partial!" => {
+            match parse_partial(args).or_else(|| Some((symbol_name(args.first()?)?, false))) {
+                Some((name, true)) => join_elements(parent, optional, vec![partial_marker(&name)]),
+                Some((name, false)) => vec![join_key(parent, optional, &partial_marker(&name))],
+                None => Vec::new(),
+            }
+        }
+        // dynamic keys e.g. `json.set! post.slug, post.title` can be anything
+        "set!" => match args.split_first() {
+            Some((key, rest)) => parse_jbuilder_value(
+                &symbol_name(key).map_or_else(|| "*".to_owned(), |x| format.apply(&x)),
+                rest,
+                body,
+                optional,
+                parent,
+                format,
+                context,
+            ),
+            None => Vec::new(),
+        },
+        "cache!" | "cache_if!" | "cache_root!" => body
+            .map(|x| parse_jbuiler_nodes(x, optional, parent, &mut format.clone(), context))
+            .unwrap_or_default(),
+        "nil!" | "null!" | "ignore_nil!" | "key_format!" | "deep_format_keys!" => Vec::new(),
+        key => parse_jbuilder_value(
+            &format.apply(key),
+            args,
+            body,
+            optional,
+            parent,
+            format,
+            context,
+        ),
+    }
+}
+
+// readable text for a condition e.g. `@post.published?() && !current_user()`
+fn condition_text(node: &Node) -> String {
+    let wrap = |node: &Node| match node {
+        Node::And(_) | Node::Or(_) => format!("({})", condition_text(node)),
+        node => condition_text(node),
+    };
+    match node {
+        Node::And(and) => format!("{} && {}", wrap(&and.lhs), wrap(&and.rhs)),
+        Node::Or(or) => format!("{} || {}", wrap(&or.lhs), wrap(&or.rhs)),
+        Node::Begin(begin) if begin.statements.len() == 1 => condition_text(&begin.statements[0]),
+        Node::Send(send) if send.method_name == "!" && send.args.is_empty() => {
+            format!("!{}", send.recv.as_deref().map(wrap).unwrap_or_default())
+        }
+        node => parse_receiver(Some(node)).to_string(),
+    }
+}
+
+fn negate(condition: &str) -> String {
+    if condition.contains(' ') {
+        format!("!({})", condition)
+    } else {
+        format!("!{}", condition)
+    }
+}
+
+// the branches of an `if`, `elsif`, `unless`, modifier, ternary or `case`, where `None` renders
+// nothing
+fn conditional_branches(node: &Node) -> Vec<(String, Option<&Node>)> {
+    let (cond, if_true, if_false) = match node {
+        Node::If(stat) => (&stat.cond, &stat.if_true, &stat.if_false),
+        Node::IfMod(stat) => (&stat.cond, &stat.if_true, &stat.if_false),
+        Node::IfTernary(stat) => {
+            return vec![
+                (condition_text(&stat.cond), Some(&*stat.if_true)),
+                ("else".to_owned(), Some(&*stat.if_false)),
+            ]
+        }
+        Node::Case(case) => {
+            let expr = case.expr.as_deref().map(condition_text);
+            let mut branches: Vec<(String, Option<&Node>)> = case
+                .when_bodies
+                .iter()
+                .filter_map(|when| match when {
+                    Node::When(when) => Some((
+                        format!(
+                            "{}when {}",
+                            expr.as_ref().map(|x| format!("{} ", x)).unwrap_or_default(),
+                            when.patterns
+                                .iter()
+                                .map(condition_text)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                        when.body.as_deref(),
+                    )),
+                    _ => None,
+                })
+                .collect();
+            branches.push(("else".to_owned(), case.else_body.as_deref()));
+            return branches;
+        }
+        _ => return Vec::new(),
+    };
+    let condition = condition_text(cond);
+    match (if_true.as_deref(), if_false.as_deref()) {
+        // `unless`
+        (None, Some(if_false)) => vec![
+            (negate(&condition), Some(if_false)),
+            ("else".to_owned(), None),
+        ],
+        // only a real `elsif` has no `end` of its own, `else if` stays a nested conditional
+        (if_true, Some(elsif @ Node::If(stat))) if stat.end_l.is_none() => {
+            let mut branches = vec![(condition, if_true)];
+            branches.append(&mut conditional_branches(elsif));
+            branches
+        }
+        (if_true, if_false) => vec![(condition, if_true), ("else".to_owned(), if_false)],
+    }
+}
+
+// marks the key added at `parent`'s level as optional e.g. `editor.name` is `editor.?name`
+fn mark_optional(key: &str, parent: &str) -> String {
+    let split = if parent.is_empty() {
+        0
+    } else {
+        parent.len() + 1
+    };
+    match key.get(split..) {
+        Some(rest) if key.starts_with(parent) && !rest.starts_with('?') => {
+            format!("{}?{}", &key[..split], rest)
+        }
+        _ => key.to_owned(),
+    }
+}
+
+// keys every branch renders are required and the rest optional, the branches are recorded so
+// differing shapes can be documented as alternatives
+fn parse_jbuilder_conditional(
+    node: &Node,
+    optional: bool,
+    parent: &str,
+    format: &mut KeyFormat,
+    context: &mut JbuilderContext,
+) -> Vec<String> {
+    let branches: Vec<ViewBranch> = conditional_branches(node)
+        .into_iter()
+        .map(|(condition, body)| ViewBranch {
+            condition,
+            keys: body
+                .map(|x| parse_jbuiler_nodes(x, optional, parent, &mut format.clone(), context))
+                .unwrap_or_default(),
+        })
+        .collect();
+    let mut results: Vec<String> = Vec::new();
+    for key in branches.iter().flat_map(|x| &x.keys) {
+        let key = if branches.iter().all(|x| x.keys.contains(key)) {
+            key.clone()
+        } else {
+            mark_optional(key, parent)
+        };
+        if !results.contains(&key) {
+            results.push(key);
+        }
+    }
+    context.conditionals.push(ViewConditional { branches });
+    results
+}
+
+fn parse_jbuiler_nodes(
+    node: &Node,
+    optional: bool,
+    parent: &str,
+    format: &mut KeyFormat,
+    context: &mut JbuilderContext,
+) -> Vec<String> {
+    let mut results = Vec::new();
+    let inherited = optional;
+    let mut optional = optional;
+    let statements = match node {
+        Node::Begin(begin) => begin.statements.iter().collect(),
+        Node::KwBegin(begin) => begin.statements.iter().collect(),
+        node => vec![node],
+    };
+    for statement in statements {
+        match statement {
+            Node::Begin(_) | Node::KwBegin(_) => results.append(&mut parse_jbuiler_nodes(
+                statement, optional, parent, format, context,
+            )),
+            Node::Block(block) => {
+                let body = block.body.as_deref();
+                let collection = match json_call(&block.call) {
+                    Some(call) if call.method_name == "set!" => call.args.get(1),
+                    Some(call) => call.args.first(),
+                    // e.g. `@posts.each do |post|`
+                    None => match &*block.call {
+                        Node::Send(send) => send.recv.as_deref(),
+                        _ => None,
+                    },
+                };
+                context.bind(block.args.as_deref(), collection);
+                match json_call(&block.call) {
+                    Some(call) => results.append(&mut parse_jbuilder_call(
+                        call, body, optional, parent, format, context,
+                    )),
+                    // e.g. `@posts.each do |post| json.set! ... end`
+                    None => results.append(
+                        &mut body
+                            .map(|x| parse_jbuiler_nodes(x, optional, parent, format, context))
+                            .unwrap_or_default(),
+                    ),
+                }
             }
+            Node::If(_) | Node::IfMod(_) | Node::IfTernary(_) | Node::Case(_) => results.append(
+                &mut parse_jbuilder_conditional(statement, optional, parent, format, context),
+            ),
+            Node::Send(_) => match json_call(statement) {
+                // nil values are left out from here on
+                Some(call) if call.method_name == "ignore_nil!" => {
+                    optional = inherited || !matches!(call.args.first(), Some(Node::False(_)))
+                }
+                Some(call) if call.method_name == "key_format!" => {
+                    format.transforms = parse_key_transforms(&call.args)
+                }
+                Some(call) if call.method_name == "deep_format_keys!" => {
+                    format.deep = parse_deep_format(&call.args)
+                }
+                Some(call) => results.append(&mut parse_jbuilder_call(
+                    call, None, optional, parent, format, context,
+                )),
+                None => {}
+            },
+            _ => {}
         }
     }
 
     results
 }
 
+#[derive(Default)]
+struct IvarFinder {
+    ivars: HashSet<String>,
+}
+
+impl Visitor for IvarFinder {
+    fn on_ivar(&mut self, node: &Ivar) {
+        self.ivars.insert(node.name.clone());
+    }
+}
+
+fn find_ivars(node: &Node) -> HashSet<String> {
+    let mut finder = IvarFinder::default();
+    finder.visit(node);
+    finder.ivars
+}
+
+#[derive(Default)]
+struct HelperCallFinder {
+    calls: HashSet<String>,
+}
+
+impl Visitor for HelperCallFinder {
+    fn on_send(&mut self, node: &Send) {
+        if node.recv.is_none() && node.method_name != "json" {
+            self.calls.insert(node.method_name.clone());
+        }
+        self.maybe_visit(&node.recv);
+        self.visit_all(&node.args);
+    }
+}
+
+fn find_helper_calls(node: &Node) -> Vec<String> {
+    let mut finder = HelperCallFinder::default();
+    finder.visit(node);
+    let mut calls: Vec<String> = finder.calls.into_iter().collect();
+    calls.sort();
+    calls
+}
+
 fn parse_jbuilder(
     parser: ParserResult,
     action: String,
     controller: String,
+    key_format: &KeyFormat,
 ) -> Result<View, String> {
     if let Some(ast) = parser.ast {
+        let mut context = JbuilderContext::default();
+        let response = parse_jbuiler_nodes(&ast, false, "", &mut key_format.clone(), &mut context);
         Ok(View {
             controller,
             method: action,
-            response: parse_jbuiler_nodes(&ast, false, ""),
+            format: None,
+            variant: None,
+            response,
+            conditionals: context.conditionals,
+            sources: context.sources.into_iter().collect(),
+            helper_calls: find_helper_calls(&ast),
             view_type: ViewType::Jbuilder,
+            instance_varaibles: find_ivars(&ast),
         })
     } else {
         Err("empty view".to_owned())
     }
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
+/// A template's filename split the way rails resolves it e.g. `show.json+phone.jbuilder`
+#[derive(Debug, PartialEq)]
+struct TemplateName {
+    action: String,
+    format: Option<String>,
+    variant: Option<String>,
+    handler: String,
+}
+
+fn parse_template_name(file_name: &str) -> TemplateName {
+    let mut parts = file_name.split('.');
+    let action = parts.next().unwrap_or_default().to_owned();
+    let mut rest: Vec<&str> = parts.collect();
+    let handler = rest.pop().unwrap_or_default().to_owned();
+    // only the last extension before the handler is the format, e.g. `show.html.erb`
+    let (format, variant) = match rest.last() {
+        Some(ext) => match ext.split_once('+') {
+            Some((format, variant)) => (Some(format), Some(variant.to_owned())),
+            None => (Some(*ext), None),
+        },
+        None => (None, None),
+    };
+    TemplateName {
+        action,
+        format: format.filter(|x| !x.is_empty()).map(|x| x.to_owned()),
+        variant,
+        handler,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn view_from_source(controller: &str, action: &str, source: &str) -> View {
+    let parser = Parser::new(source.as_bytes(), Default::default()).do_parse();
+    parse_jbuilder(
+        parser,
+        format!("{}.json.jbuilder", action),
+        controller.to_owned(),
+        &KeyFormat::default(),
+    )
+    .unwrap()
+}
+
+// `comments/comment` is `comments/_comment.json.jbuilder` and `comment` is relative to `dir`
+fn find_partial<'a>(
+    views: &'a HashMap<String, HashMap<String, View>>,
+    dir: &str,
+    name: &str,
+) -> Option<&'a View> {
+    let (dir, name) = name.rsplit_once('/').unwrap_or((dir, name));
+    views
+        .get(dir)?
+        .get(&format!("_{}", name.split('.').next().unwrap_or_default()))
+}
+
+// a partial's own relative partials are relative to its directory, not the caller's
+fn qualify_partials(key: &str, dir: &str) -> String {
+    match key.find("partial!(") {
+        Some(start)
+            if !key[start..]
+                .split(')')
+                .next()
+                .unwrap_or_default()
+                .contains('/') =>
+        {
+            format!("{}{}/{}", &key[..start + 9], dir, &key[start + 9..])
+        }
+        _ => key.to_owned(),
+    }
+}
+
+// replaces each `partial!(name)` marker with the keys of the partial it renders, `None` when
+// there are none to replace. The helpers the partials call are added to `helper_calls`.
+fn expand_partials(
+    keys: &[String],
+    dir: &str,
+    views: &HashMap<String, HashMap<String, View>>,
+    helper_calls: &mut Vec<String>,
+) -> Option<Vec<String>> {
+    let mut changed = false;
+    let mut results = Vec::new();
+    for key in keys {
+        let partial = key
+            .find("partial!(")
+            .and_then(|start| Some((start, start + key[start..].find(')')? + 1)));
+        match partial.and_then(|(start, end)| {
+            Some((
+                &key[start..end],
+                find_partial(views, dir, &key[start + 9..end - 1])
+                    .filter(|x| !x.response.is_empty())?,
+            ))
+        }) {
+            Some((marker, partial)) => {
+                changed = true;
+                helper_calls.extend(partial.helper_calls.iter().cloned());
+                results.extend(
+                    partial.response.iter().map(|x| {
+                        key.replacen(marker, &qualify_partials(x, &partial.controller), 1)
+                    }),
+                );
+            }
+            None => results.push(key.clone()),
+        }
+    }
+    changed.then_some(results)
+}
+
+pub(crate) fn resolve_partials(views: &mut HashMap<String, HashMap<String, View>>) {
+    // partials can render partials
+    for _ in 0..5 {
+        let mut resolved = Vec::new();
+        for (dir, templates) in views.iter() {
+            for (action, view) in templates {
+                let mut helper_calls = view.helper_calls.clone();
+                let response = expand_partials(&view.response, dir, views, &mut helper_calls);
+                let mut conditionals = view.conditionals.clone();
+                let mut changed = response.is_some();
+                for branch in conditionals.iter_mut().flat_map(|x| &mut x.branches) {
+                    if let Some(keys) = expand_partials(&branch.keys, dir, views, &mut helper_calls)
+                    {
+                        branch.keys = keys;
+                        changed = true;
+                    }
+                }
+                if changed {
+                    helper_calls.sort();
+                    helper_calls.dedup();
+                    resolved.push((
+                        dir.clone(),
+                        action.clone(),
+                        response,
+                        conditionals,
+                        helper_calls,
+                    ));
+                }
+            }
+        }
+        if resolved.is_empty() {
+            break;
+        }
+        for (dir, action, response, conditionals, helper_calls) in resolved {
+            if let Some(view) = views.get_mut(&dir).and_then(|x| x.get_mut(&action)) {
+                if let Some(response) = response {
+                    view.response = response;
+                }
+                view.conditionals = conditionals;
+                view.helper_calls = helper_calls;
+            }
+        }
+    }
 }
 
 pub fn parse_view_files(
     path: &Path,
+    key_format: &KeyFormat,
     views: &mut HashMap<String, HashMap<String, View>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     for entry in WalkDir::new(path)
         .into_iter()
-        .filter_entry(|e| -> bool { !is_hidden(e) })
+        .filter_entry(|e| -> bool { !utils::is_hidden(e) })
         .filter_map(|e| e.ok())
     {
         let f = entry.path();
-        let name = f.display().to_string();
-        if f.is_file() && (name.ends_with(".jbuilder") || name.ends_with(".jb")) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if f.is_file() && (file_name.ends_with(".jbuilder") || file_name.ends_with(".jb")) {
+            // keyed by the full directory under the views root e.g. `api/v1/users`
             let controller = f
                 .parent()
-                .unwrap()
-                .display()
-                .to_string()
-                .split('/')
-                .last()
-                .unwrap()
-                .to_string();
-            let action = f
-                .display()
-                .to_string()
-                .split('/')
-                .last()
-                .unwrap()
-                .to_string();
-            let views_controller = views.entry(controller.clone()).or_insert_with(HashMap::new);
+                .and_then(|x| x.strip_prefix(path).ok())
+                .map(|x| {
+                    x.components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/")
+                })
+                .unwrap_or_default();
+            let template = parse_template_name(&file_name);
+            if template.handler != "jbuilder"
+                || !matches!(template.format.as_deref(), None | Some("json"))
+            {
+                continue;
+            }
             let parser = Parser::new(&fs::read(entry.path())?, Default::default()).do_parse();
+            let mut view = parse_jbuilder(parser, file_name, controller.clone(), key_format)?;
+            let key = match &template.variant {
+                Some(variant) => format!("{}+{}", template.action, variant),
+                None => template.action,
+            };
+            view.format = template.format;
+            view.variant = template.variant;
 
-            if action.ends_with(".jbuilder") {
-                views_controller.insert(
-                    action.trim_end_matches(".jbuilder").to_owned(),
-                    parse_jbuilder(parser, action, controller)?,
-                );
+            let views_controller = views.entry(controller).or_default();
+            // `show.json.jbuilder` wins over a format-less `show.jbuilder`
+            if view.format.is_some() || !views_controller.contains_key(&key) {
+                views_controller.insert(key, view);
             }
         }
     }
+    resolve_partials(views);
     Ok(())
 }
 
 #[cfg(test)]
 mod views_tests {
-    use lib_ruby_parser::Parser;
+    use lib_ruby_parser::{Node, Parser};
 
     use pretty_assertions::assert_eq;
 
-    use super::parse_jbuiler_nodes;
+    use std::collections::{HashMap, HashSet};
+
+    use super::{
+        find_ivars, parse_jbuiler_nodes, parse_key_transforms, parse_template_name,
+        resolve_partials, JbuilderContext, KeyFormat, KeyTransform, TemplateName,
+    };
+    use crate::types::{View, ViewConditional, ViewType};
 
     fn helper(input: &str) -> Vec<String> {
         let mut results = parse_jbuiler_nodes(
@@ -199,6 +901,8 @@ mod views_tests {
                 .unwrap(),
             false,
             "",
+            &mut KeyFormat::default(),
+            &mut JbuilderContext::default(),
         );
         results.sort();
         results
@@ -217,7 +921,6 @@ mod views_tests {
                 "uploads.file_type".to_owned(),
                 "uploads.id".to_owned(),
                 "uploads.stored_filename".to_owned(),
-                "uploads.upload".to_owned(),
                 "uploads.user_filename".to_owned(),
             ]
         );
@@ -241,7 +944,6 @@ mod views_tests {
                 "uploads.file_type".to_owned(),
                 "uploads.id".to_owned(),
                 "uploads.stored_filename".to_owned(),
-                "uploads.upload".to_owned(),
                 "uploads.user_filename".to_owned(),
             ]
         );
@@ -298,6 +1000,57 @@ mod views_tests {
         assert_eq!(helper(input), ["admin".to_owned()]);
     }
 
+    #[test]
+    fn instance_varaibles() {
+        let input = "
+        json.(@data, :id)
+        if @options && @options[:include_upload_links]
+            json.url @data.download_link
+        end
+        ";
+        let mut results = find_ivars(
+            &Parser::new(input.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+        )
+        .into_iter()
+        .collect::<Vec<String>>();
+        results.sort();
+        assert_eq!(results, ["@data", "@options"]);
+    }
+
+    #[test]
+    fn template_names() {
+        assert_eq!(
+            parse_template_name("show.json.jbuilder"),
+            TemplateName {
+                action: "show".to_owned(),
+                format: Some("json".to_owned()),
+                variant: None,
+                handler: "jbuilder".to_owned(),
+            }
+        );
+        assert_eq!(
+            parse_template_name("index.json+phone.jbuilder"),
+            TemplateName {
+                action: "index".to_owned(),
+                format: Some("json".to_owned()),
+                variant: Some("phone".to_owned()),
+                handler: "jbuilder".to_owned(),
+            }
+        );
+        assert_eq!(
+            parse_template_name("_comment.jbuilder"),
+            TemplateName {
+                action: "_comment".to_owned(),
+                format: None,
+                variant: None,
+                handler: "jbuilder".to_owned(),
+            }
+        );
+    }
+
     #[test]
     fn array_exclamation_point() {
         let input = "
@@ -309,13 +1062,71 @@ mod views_tests {
         assert_eq!(
             helper(input),
             [
-                "[id]",
                 "[display_name]",
+                "[id]",
+                "[last_seen]",
                 "[name]",
-                "[last_name]",
                 "[status]"
             ]
         );
+        assert_eq!(
+            helper("json.array! @accounts, :id, :name"),
+            ["[id]", "[name]"]
+        );
+        assert_eq!(helper("json.tags do json.array! @tags end"), ["tags.[]"]);
+    }
+
+    #[test]
+    fn extract_set_and_merge() {
+        let input = "
+        json.extract! @post, :id, :title
+        json.set! :author_name, @post.author.name
+        json.set! @post.slug do
+            json.url post_url(@post)
+        end
+        json.merge!({ published: true, meta: { views: 1 } })
+        json.comments @post.comments, :id, :body
+        ";
+
+        assert_eq!(
+            helper(input),
+            [
+                "*.url",
+                "author_name",
+                "comments.body",
+                "comments.id",
+                "id",
+                "meta.views",
+                "published",
+                "title"
+            ]
+        );
+    }
+
+    #[test]
+    fn child_cache_and_nil() {
+        let input = "
+        json.cache! ['v1', @post] do
+            json.id @post.id
+        end
+        json.comments do
+            @post.comments.each do |comment|
+                json.child! do
+                    json.body comment.body
+                end
+            end
+        end
+        json.editor do
+            json.nil!
+        end
+        json.ignore_nil!
+        json.summary @post.summary
+        ";
+
+        assert_eq!(
+            helper(input),
+            ["?summary", "comments.[body]", "editor", "id"]
+        );
     }
 
     #[test]
@@ -323,7 +1134,247 @@ mod views_tests {
         let input = "
         json.partial! 'comments/comment', comment: @comment
         ";
-        todo!("return type hmmm or a string for the vector, I guess it will have to be some kinda string in the vec so we can get the position in the json correct");
-        assert_eq!(helper(input), ["admin".to_owned()]);
+        assert_eq!(helper(input), ["partial!(comments/comment)"]);
+
+        let input = "
+        json.author @post.author, partial: 'users/user', as: :user
+        json.array! @post.tags, partial: 'tag', as: :tag
+        json.partial! partial: 'comments/comment', collection: @post.comments, as: :comment
+        ";
+        assert_eq!(
+            helper(input),
+            [
+                "[partial!(comments/comment)]",
+                "[partial!(tag)]",
+                "author.partial!(users/user)"
+            ]
+        );
+    }
+
+    #[test]
+    fn partials_are_resolved() {
+        let view = |controller: &str, response: &[&str]| View {
+            controller: controller.to_owned(),
+            method: String::new(),
+            format: None,
+            variant: None,
+            response: response.iter().map(|x| x.to_string()).collect(),
+            conditionals: Vec::new(),
+            sources: HashMap::new(),
+            helper_calls: Vec::new(),
+            view_type: ViewType::Jbuilder,
+            instance_varaibles: HashSet::new(),
+        };
+        let mut views: HashMap<String, HashMap<String, View>> = HashMap::new();
+        let posts = views.entry("posts".to_owned()).or_default();
+        posts.insert(
+            "show".to_owned(),
+            view(
+                "posts",
+                &["id", "author.partial!(users/user)", "[partial!(tag)]"],
+            ),
+        );
+        posts.insert("_tag".to_owned(), view("posts", &["name"]));
+        views.entry("users".to_owned()).or_default().insert(
+            "_user".to_owned(),
+            view("users", &["id", "partial!(avatar)"]),
+        );
+        views
+            .entry("users".to_owned())
+            .or_default()
+            .insert("_avatar".to_owned(), view("users", &["url"]));
+        views
+            .get_mut("posts")
+            .unwrap()
+            .get_mut("_tag")
+            .unwrap()
+            .helper_calls = vec!["tag_label".to_owned()];
+        views
+            .get_mut("users")
+            .unwrap()
+            .get_mut("_avatar")
+            .unwrap()
+            .helper_calls = vec!["avatar_url".to_owned()];
+
+        resolve_partials(&mut views);
+        assert_eq!(
+            views["posts"]["show"].response,
+            ["id", "author.id", "author.url", "[name]"]
+        );
+        assert_eq!(
+            views["posts"]["show"].helper_calls,
+            ["avatar_url", "tag_label"]
+        );
+    }
+
+    #[test]
+    fn key_format() {
+        let parse = |input: &str, format: &KeyFormat| -> Vec<String> {
+            let mut results = parse_jbuiler_nodes(
+                &Parser::new(input.as_bytes(), Default::default())
+                    .do_parse()
+                    .ast
+                    .unwrap(),
+                false,
+                "",
+                &mut format.clone(),
+                &mut JbuilderContext::default(),
+            );
+            results.sort();
+            results
+        };
+        let camel = KeyFormat {
+            transforms: vec![KeyTransform::Camelize(true)],
+            deep: false,
+        };
+        let input = "
+        json.(@post, :id, :created_at)
+        json.author_name @post.author.name
+        json.merge!({ view_count: 1 })
+        json.recent_comments do
+            json.key_format! :upcase, dasherize: []
+            json.comment_body 'x'
+        end
+        json.last_seen_at @post.last_seen_at
+        ";
+        assert_eq!(
+            parse(input, &camel),
+            [
+                "authorName",
+                "createdAt",
+                "id",
+                "lastSeenAt",
+                "recentComments.COMMENT-BODY",
+                "view_count"
+            ]
+        );
+        assert_eq!(
+            parse(
+                "json.deep_format_keys!\njson.merge!({ meta: { view_count: 1 } })",
+                &camel
+            ),
+            ["meta.viewCount"]
+        );
+        assert_eq!(
+            parse(
+                "json.key_format! camelize: :upper\njson.created_at 1",
+                &KeyFormat::default()
+            ),
+            ["CreatedAt"]
+        );
+
+        let node = Parser::new(
+            "Jbuilder.key_format camelize: :lower".as_bytes(),
+            Default::default(),
+        )
+        .do_parse()
+        .ast
+        .unwrap();
+        match node {
+            Node::Send(send) => assert_eq!(
+                parse_key_transforms(&send.args),
+                [KeyTransform::Camelize(true)]
+            ),
+            _ => panic!("expected a send"),
+        }
+    }
+
+    #[test]
+    fn conditional_alternatives() {
+        let input = "
+        json.id @post.id
+        if @post.published? && !@post.draft?
+            json.published_at @post.published_at
+            json.title @post.title
+        elsif @post.scheduled?
+            json.scheduled_at @post.scheduled_at
+            json.title @post.title
+        else
+            json.title @post.title
+        end
+        json.summary @post.summary unless @post.private?
+        @post.author ? json.author(@post.author.name) : json.anonymous(true)
+        case @post.kind
+        when :video then json.duration @post.duration
+        when :image, :gallery then json.url @post.url
+        end
+        if @post.pinned?
+            json.pinned true
+        else if @post.featured?
+            json.featured true
+        end
+        end
+        ";
+        let mut context = JbuilderContext::default();
+        let mut results = parse_jbuiler_nodes(
+            &Parser::new(input.as_bytes(), Default::default())
+                .do_parse()
+                .ast
+                .unwrap(),
+            false,
+            "",
+            &mut KeyFormat::default(),
+            &mut context,
+        );
+        let conditionals = context.conditionals;
+        results.sort();
+        assert_eq!(
+            results,
+            [
+                "?anonymous",
+                "?author",
+                "?duration",
+                "?featured",
+                "?pinned",
+                "?published_at",
+                "?scheduled_at",
+                "?summary",
+                "?url",
+                "id",
+                "title"
+            ]
+        );
+
+        let describe = |conditional: &ViewConditional| -> Vec<String> {
+            conditional
+                .branches
+                .iter()
+                .map(|x| format!("{}: {}", x.condition, x.keys.join(",")))
+                .collect()
+        };
+        assert_eq!(
+            describe(&conditionals[0]),
+            [
+                "@post.published?() && !@post.draft?(): published_at,title",
+                "@post.scheduled?(): scheduled_at,title",
+                "else: title"
+            ]
+        );
+        assert!(conditionals[0].is_one_of());
+        assert_eq!(
+            describe(&conditionals[1]),
+            ["!@post.private?(): summary", "else: "]
+        );
+        assert!(!conditionals[1].is_one_of());
+        assert_eq!(
+            describe(&conditionals[2]),
+            ["@post.author(): author", "else: anonymous"]
+        );
+        assert_eq!(
+            describe(&conditionals[3]),
+            [
+                "@post.kind() when video: duration",
+                "@post.kind() when image, gallery: url",
+                "else: "
+            ]
+        );
+        assert_eq!(
+            describe(&conditionals[4]),
+            ["@post.featured?(): featured", "else: "]
+        );
+        assert_eq!(
+            describe(&conditionals[5]),
+            ["@post.pinned?(): pinned", "else: ?featured"]
+        );
     }
 }
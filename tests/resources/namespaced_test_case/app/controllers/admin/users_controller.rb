@@ -0,0 +1,7 @@
+module Admin
+  class UsersController < ApplicationController
+    def index
+      @users = User.where(role: params[:role])
+    end
+  end
+end
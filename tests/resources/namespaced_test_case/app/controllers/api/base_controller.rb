@@ -0,0 +1,11 @@
+module Api
+  class BaseController < ApplicationController
+    before_action :authenticate
+
+    private
+
+    def authenticate
+      params[:token]
+    end
+  end
+end
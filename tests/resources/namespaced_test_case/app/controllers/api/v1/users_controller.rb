@@ -0,0 +1,9 @@
+module Api
+  module V1
+    class UsersController < BaseController
+      def show
+        @user = User.find(params[:user_id])
+      end
+    end
+  end
+end
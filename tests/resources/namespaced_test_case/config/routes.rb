@@ -0,0 +1,13 @@
+Rails.application.routes.draw do
+  resources :users, only: :show
+
+  namespace :admin do
+    resources :users, only: :index
+  end
+
+  namespace :api do
+    namespace :v1 do
+      resources :users, only: :show
+    end
+  end
+end
@@ -0,0 +1,10 @@
+Rails.application.routes.draw do
+  draw :admin
+
+  get 'status', to: 'status#show'
+
+  mount Api::Engine => '/api'
+  mount Blog::Engine, at: '/blog'
+  mount Sidekiq::Web => '/sidekiq'
+  mount ActionCable.server => '/cable', as: :cable
+end
@@ -0,0 +1,5 @@
+Api::Engine.routes.draw do
+  resources :posts, only: [:index, :show] do
+    resources :comments, only: :index
+  end
+end
@@ -0,0 +1,5 @@
+module Api
+  class Engine < ::Rails::Engine
+    isolate_namespace Api
+  end
+end
@@ -0,0 +1,4 @@
+module Blog
+  class Engine < ::Rails::Engine
+  end
+end